@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Manager, Window};
+
+const AUDIT_FILE: &str = "audit.log";
+// The log is rotated to this file once it grows past MAX_AUDIT_BYTES
+const ROTATED_AUDIT_FILE: &str = "audit.log.1";
+const MAX_AUDIT_BYTES: u64 = 5 * 1024 * 1024;
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 1000;
+
+// Serialises appends so concurrent writers never interleave lines
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    timestamp: u64,
+    action: String,
+    details: Value,
+}
+
+fn audit_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    Ok(dir)
+}
+
+fn append(dir: &Path, line: &str) -> Result<(), String> {
+    let path = dir.join(AUDIT_FILE);
+    let _guard = AUDIT_LOCK.lock().unwrap();
+
+    // Keep one older file around, anything before it is dropped
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size >= MAX_AUDIT_BYTES {
+        std::fs::rename(&path, dir.join(ROTATED_AUDIT_FILE)).map_err(|e| e.to_string())?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+// Append an entry to the audit trail, one JSON object per line
+pub(crate) fn record(app: &AppHandle, action: &str, details: Value) {
+    let entry = AuditEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        action: action.to_string(),
+        details,
+    };
+
+    let result = audit_dir(app).and_then(|dir| {
+        let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        append(&dir, &line)
+    });
+
+    if let Err(e) = result {
+        eprintln!("[DEBUG] Failed to write audit entry: {}", e);
+    }
+}
+
+// A page of the audit trail, newest first. Only the lines of the requested page
+// are kept in memory while the files are read.
+#[command]
+pub fn get_audit_log(
+    window: Window,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, String> {
+    let dir = audit_dir(window.app_handle())?;
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let keep = offset.saturating_add(limit);
+
+    let mut newest: VecDeque<String> = VecDeque::with_capacity(keep.min(MAX_PAGE_SIZE * 2));
+    for name in [ROTATED_AUDIT_FILE, AUDIT_FILE] {
+        let file = match std::fs::File::open(dir.join(name)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Failed to open audit log: {}", e)),
+        };
+        for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+            if newest.len() == keep {
+                newest.pop_front();
+            }
+            newest.push_back(line);
+        }
+    }
+
+    Ok(newest
+        .iter()
+        .rev()
+        .skip(offset)
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
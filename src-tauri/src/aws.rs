@@ -7,7 +7,7 @@ use tokio::sync::Mutex;
 use serde::{Deserialize, Serialize};
 
 // Helper function to get PATH with common locations
-fn get_path_with_common_locations() -> String {
+pub(crate) fn get_path_with_common_locations() -> String {
     let mut paths = Vec::new();
     
     // Get existing PATH
@@ -91,7 +91,7 @@ async fn check_aws_cli() -> Result<(), String> {
     }
 }
 
-async fn run_json_async(cmd: &str, args: &[&str]) -> Result<Value, String> {
+pub(crate) async fn run_json_async(cmd: &str, args: &[&str]) -> Result<Value, String> {
     // Check if AWS CLI is available before running commands
     if cmd == "aws" {
         check_aws_cli().await?;
@@ -521,3 +521,118 @@ async fn check_tool(cmd: &str, args: &[&str]) -> Result<String, String> {
     }
 }
 
+
+// Extract the short task id from a task ARN (arn:aws:ecs:region:account:task/cluster/id)
+pub(crate) fn task_id_from_arn(task_arn: &str) -> String {
+    task_arn.rsplit('/').next().unwrap_or(task_arn).to_string()
+}
+
+// Extract the cluster name from a cluster ARN (arn:aws:ecs:region:account:cluster/name)
+pub(crate) fn cluster_name_from_arn(cluster: &str) -> String {
+    cluster.rsplit('/').next().unwrap_or(cluster).to_string()
+}
+
+// End a Session Manager session, for ExecuteCommand sessions that never got connected
+pub(crate) async fn terminate_ssm_session(
+    profile: &str,
+    region: &str,
+    session_id: &str,
+) -> Result<(), String> {
+    run_json_async(
+        "aws",
+        &[
+            "ssm",
+            "terminate-session",
+            "--session-id",
+            session_id,
+            "--region",
+            region,
+            "--profile",
+            profile,
+            "--output",
+            "json",
+        ],
+    )
+    .await
+    .map(|_| ())
+}
+
+// ARNs of the running tasks in a cluster, optionally limited to one service
+pub(crate) async fn list_running_tasks(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    service: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let mut args = vec![
+        "ecs",
+        "list-tasks",
+        "--cluster",
+        cluster,
+        "--desired-status",
+        "RUNNING",
+        "--region",
+        region,
+        "--profile",
+        profile,
+        "--output",
+        "json",
+    ];
+    if let Some(service) = service {
+        args.push("--service-name");
+        args.push(service);
+    }
+
+    let v = run_json_async("aws", &args).await?;
+
+    let arns = v["taskArns"]
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .filter_map(|x| x.as_str().map(|s| s.to_string()))
+        .collect();
+
+    Ok(arns)
+}
+
+// The running tasks in a cluster as described by describe-tasks, optionally
+// limited to one service. Only the first 100 tasks are described.
+pub(crate) async fn describe_running_tasks(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    service: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let task_arns = list_running_tasks(profile, region, cluster, service).await?;
+    if task_arns.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut args = vec![
+        "ecs",
+        "describe-tasks",
+        "--cluster",
+        cluster,
+        "--region",
+        region,
+        "--profile",
+        profile,
+        "--output",
+        "json",
+        "--tasks",
+    ];
+    // describe-tasks accepts at most 100 tasks per call
+    args.extend(task_arns.iter().take(100).map(|s| s.as_str()));
+    let v = run_json_async("aws", &args).await?;
+
+    Ok(v["tasks"]
+        .as_array()
+        .map(|tasks| {
+            tasks
+                .iter()
+                .filter(|t| t["lastStatus"].as_str() == Some("RUNNING"))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default())
+}
@@ -0,0 +1,183 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use aws_sdk_ecs::types::ClusterField;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::command;
+
+use crate::ecs_api::{self, ExecConfiguration};
+
+// DescribeClusters accepts at most this many clusters per call
+const DESCRIBE_BATCH: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterDetails {
+    pub cluster_name: String,
+    pub cluster_arn: String,
+    // ACTIVE, PROVISIONING, DEPROVISIONING, FAILED or INACTIVE
+    pub status: Option<String>,
+    pub running_tasks_count: i32,
+    pub pending_tasks_count: i32,
+    pub active_services_count: i32,
+    pub registered_container_instances_count: i32,
+    pub capacity_providers: Vec<String>,
+    pub default_capacity_provider_strategy: Vec<CapacityProviderWeight>,
+    // enabled, enhanced or disabled, None when the account default applies
+    pub container_insights: Option<String>,
+    // Per launch type counts, e.g. runningFargateTasksCount
+    pub statistics: BTreeMap<String, String>,
+    pub exec_configuration: ExecConfiguration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterFailure {
+    // The cluster as requested, or its ARN
+    pub cluster: String,
+    // e.g. MISSING
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterOverview {
+    pub clusters: Vec<ClusterDetails>,
+    // Clusters that could not be described, the rest are still returned
+    pub failures: Vec<ClusterFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacityProviderWeight {
+    pub capacity_provider: String,
+    pub weight: i32,
+    pub base: i32,
+}
+
+fn cluster_details(cluster: &aws_sdk_ecs::types::Cluster) -> ClusterDetails {
+    ClusterDetails {
+        cluster_name: cluster.cluster_name().unwrap_or_default().to_string(),
+        cluster_arn: cluster.cluster_arn().unwrap_or_default().to_string(),
+        status: cluster.status().map(|s| s.to_string()),
+        running_tasks_count: cluster.running_tasks_count(),
+        pending_tasks_count: cluster.pending_tasks_count(),
+        active_services_count: cluster.active_services_count(),
+        registered_container_instances_count: cluster.registered_container_instances_count(),
+        capacity_providers: cluster.capacity_providers().to_vec(),
+        default_capacity_provider_strategy: cluster
+            .default_capacity_provider_strategy()
+            .iter()
+            .map(|s| CapacityProviderWeight {
+                capacity_provider: s.capacity_provider().to_string(),
+                weight: s.weight(),
+                base: s.base(),
+            })
+            .collect(),
+        container_insights: cluster
+            .settings()
+            .iter()
+            .find(|s| s.name().map(|n| n.as_str()) == Some("containerInsights"))
+            .and_then(|s| s.value())
+            .map(|s| s.to_string()),
+        statistics: cluster
+            .statistics()
+            .iter()
+            .filter_map(|kv| Some((kv.name()?.to_string(), kv.value()?.to_string())))
+            .collect(),
+        exec_configuration: ecs_api::exec_configuration_of(cluster),
+    }
+}
+
+async fn list_cluster_arns(profile: &str, region: &str) -> Result<Vec<String>, String> {
+    let client = ecs_api::client(profile, region).await;
+    let mut arns = Vec::new();
+    let mut next_token = None;
+
+    loop {
+        let out = client
+            .list_clusters()
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(|e| format!("ListClusters failed: {}", DisplayErrorContext(e)))?;
+        arns.extend(out.cluster_arns().iter().cloned());
+        next_token = out.next_token().map(|s| s.to_string());
+        if next_token.is_none() {
+            return Ok(arns);
+        }
+    }
+}
+
+// Describe clusters by name or ARN, in the order requested
+async fn describe_clusters(
+    profile: &str,
+    region: &str,
+    clusters: &[String],
+) -> Result<ClusterOverview, String> {
+    let client = ecs_api::client(profile, region).await;
+    let mut details = Vec::with_capacity(clusters.len());
+    let mut failures = Vec::new();
+
+    for batch in clusters.chunks(DESCRIBE_BATCH) {
+        let out = client
+            .describe_clusters()
+            .set_clusters(Some(batch.to_vec()))
+            .include(ClusterField::Configurations)
+            .include(ClusterField::Settings)
+            .include(ClusterField::Statistics)
+            .send()
+            .await
+            .map_err(|e| format!("DescribeClusters failed: {}", DisplayErrorContext(e)))?;
+
+        failures.extend(out.failures().iter().map(|f| ClusterFailure {
+            cluster: f.arn().unwrap_or_default().to_string(),
+            reason: f.reason().unwrap_or("unknown reason").to_string(),
+        }));
+        details.extend(out.clusters().iter().map(cluster_details));
+    }
+
+    // DescribeClusters does not keep the order it was asked in
+    let position = |c: &ClusterDetails| {
+        clusters
+            .iter()
+            .position(|requested| *requested == c.cluster_arn || *requested == c.cluster_name)
+            .unwrap_or(usize::MAX)
+    };
+    details.sort_by_key(position);
+
+    Ok(ClusterOverview {
+        clusters: details,
+        failures,
+    })
+}
+
+// Clusters with their task, service and instance counts. Without a list,
+// every cluster in the region is described, the busiest first.
+#[command]
+pub async fn ecs_describe_clusters(
+    profile: String,
+    region: String,
+    clusters: Option<Vec<String>>,
+) -> Result<ClusterOverview, String> {
+    match clusters {
+        Some(clusters) if !clusters.is_empty() => {
+            describe_clusters(&profile, &region, &clusters).await
+        }
+        _ => {
+            let arns = list_cluster_arns(&profile, &region).await?;
+            let mut overview = describe_clusters(&profile, &region, &arns).await?;
+            overview.clusters.sort_by(|a, b| {
+                let activity = |c: &ClusterDetails| {
+                    (
+                        c.running_tasks_count + c.pending_tasks_count,
+                        c.active_services_count,
+                    )
+                };
+                activity(b)
+                    .cmp(&activity(a))
+                    .then_with(|| a.cluster_name.cmp(&b.cluster_name))
+            });
+            Ok(overview)
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// How long a confirmation token stays valid after it is issued
+const TOKEN_TTL: Duration = Duration::from_secs(120);
+
+struct PendingAction {
+    kind: &'static str,
+    payload: Value,
+    expires: Instant,
+}
+
+static PENDING_ACTIONS: Mutex<Option<HashMap<String, PendingAction>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationRequest {
+    token: String,
+    // What will happen, shown to the user before they confirm
+    summary: String,
+    expires_in_secs: u64,
+}
+
+// Register a destructive action and hand back a single-use token. The action is
+// stored here so the confirming call cannot swap in different parameters.
+pub(crate) fn issue(kind: &'static str, summary: String, payload: Value) -> ConfirmationRequest {
+    let token = uuid::Uuid::new_v4().to_string();
+    let mut pending = PENDING_ACTIONS.lock().unwrap();
+    let pending = pending.get_or_insert_with(HashMap::new);
+
+    let now = Instant::now();
+    pending.retain(|_, action| action.expires > now);
+    pending.insert(
+        token.clone(),
+        PendingAction {
+            kind,
+            payload,
+            expires: now + TOKEN_TTL,
+        },
+    );
+
+    ConfirmationRequest {
+        token,
+        summary,
+        expires_in_secs: TOKEN_TTL.as_secs(),
+    }
+}
+
+// Consume a token issued for the given kind of action and return its parameters
+pub(crate) fn redeem(kind: &'static str, token: &str) -> Result<Value, String> {
+    let mut pending = PENDING_ACTIONS.lock().unwrap();
+    let action = pending
+        .as_mut()
+        .and_then(|p| p.remove(token))
+        .ok_or("Confirmation token is invalid or was already used")?;
+
+    if action.kind != kind {
+        return Err("Confirmation token does not match this action".to_string());
+    }
+    if action.expires <= Instant::now() {
+        return Err("Confirmation token has expired".to_string());
+    }
+    Ok(action.payload)
+}
@@ -0,0 +1,395 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use aws_sdk_ecs::types::{ContainerOverride, ManagedAgentName, Service, TaskOverride};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, Window};
+use tokio::sync::oneshot;
+
+use crate::audit;
+use crate::aws::task_id_from_arn;
+use crate::ecs_api;
+use crate::exec::ExecTarget;
+use crate::task_definitions::describe_task_definition;
+use crate::terminal::{self, SessionInfo, SessionTransport};
+
+const DEFAULT_TTL_SECS: u64 = 3600;
+const MAX_TTL_SECS: u64 = 12 * 3600;
+// The sleep outlives the TTL by this much, so the task still ends by itself
+// if the app quits before it could stop it
+const SLEEP_GRACE_SECS: u64 = 300;
+const READY_POLL_INTERVAL: Duration = Duration::from_secs(3);
+const READY_TIMEOUT: Duration = Duration::from_secs(300);
+// Shows up in the console and in list-tasks --started-by
+const STARTED_BY: &str = "exec-ecs-ui-debug";
+
+// Debug tasks by the session attached to them, signalled when the session ends
+static DEBUG_TASKS: Mutex<Option<HashMap<String, oneshot::Sender<()>>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugTask {
+    task_arn: String,
+    task_definition: String,
+    container: String,
+    // Milliseconds since the epoch
+    expires_at: i64,
+    session: SessionInfo,
+}
+
+fn emit_progress(window: &Window, session_id: &str, message: String) {
+    let _ = window.emit(&format!("debug-task:progress:{}", session_id), message);
+}
+
+async fn describe_service(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    service: &str,
+) -> Result<Service, String> {
+    let out = ecs_api::client(profile, region)
+        .await
+        .describe_services()
+        .cluster(cluster)
+        .services(service)
+        .send()
+        .await
+        .map_err(|e| format!("DescribeServices failed: {}", DisplayErrorContext(e)))?;
+    out.services()
+        .first()
+        .cloned()
+        .ok_or_else(|| format!("Service {} not found", service))
+}
+
+// Run a copy of the service's task that sleeps instead of serving, with the
+// service's network placement so it sees the same VPC, subnets and security groups
+async fn run_debug_task(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    service: &Service,
+    container: &str,
+    task_definition: &str,
+    sleep_secs: u64,
+) -> Result<String, String> {
+    let overrides = TaskOverride::builder()
+        .container_overrides(
+            ContainerOverride::builder()
+                .name(container)
+                .command("sleep")
+                .command(sleep_secs.to_string())
+                .build(),
+        )
+        .build();
+    let strategy = service.capacity_provider_strategy().to_vec();
+
+    let out = ecs_api::client(profile, region)
+        .await
+        .run_task()
+        .cluster(cluster)
+        .task_definition(task_definition)
+        .count(1)
+        .enable_execute_command(true)
+        .started_by(STARTED_BY)
+        .overrides(overrides)
+        .set_network_configuration(service.network_configuration().cloned())
+        .set_launch_type(service.launch_type().cloned())
+        .set_capacity_provider_strategy((!strategy.is_empty()).then_some(strategy))
+        .set_platform_version(service.platform_version().map(|s| s.to_string()))
+        .send()
+        .await
+        .map_err(|e| format!("RunTask failed: {}", DisplayErrorContext(e)))?;
+
+    if let Some(failure) = out.failures().first() {
+        return Err(format!(
+            "RunTask failed: {}",
+            failure.reason().unwrap_or("unknown reason")
+        ));
+    }
+    out.tasks()
+        .first()
+        .and_then(|t| t.task_arn())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "RunTask started no task".to_string())
+}
+
+// Wait until the task runs and the exec agent in the container is up
+async fn wait_until_exec_ready(
+    window: &Window,
+    session_id: &str,
+    target: &ExecTarget,
+) -> Result<(), String> {
+    let client = ecs_api::client(&target.profile, &target.region).await;
+    let started = Instant::now();
+    let mut last_status = String::new();
+
+    loop {
+        let out = client
+            .describe_tasks()
+            .cluster(&target.cluster)
+            .tasks(&target.task)
+            .send()
+            .await
+            .map_err(|e| format!("DescribeTasks failed: {}", DisplayErrorContext(e)))?;
+        let task = out.tasks().first().ok_or("Debug task not found")?;
+
+        let status = task.last_status().unwrap_or("PENDING").to_string();
+        if status == "STOPPED" {
+            return Err(format!(
+                "Debug task stopped: {}",
+                task.stopped_reason().unwrap_or("no reason given")
+            ));
+        }
+        if status != last_status {
+            emit_progress(window, session_id, format!("Task is {}", status));
+            last_status = status.clone();
+        }
+
+        let agent_running = task
+            .containers()
+            .iter()
+            .find(|c| c.name() == Some(target.container.as_str()))
+            .is_some_and(|c| {
+                c.managed_agents().iter().any(|a| {
+                    a.name() == Some(&ManagedAgentName::ExecuteCommandAgent)
+                        && a.last_status() == Some("RUNNING")
+                })
+            });
+        if status == "RUNNING" && agent_running {
+            return Ok(());
+        }
+
+        if started.elapsed() > READY_TIMEOUT {
+            return Err("Timed out waiting for the debug task to become exec-ready".to_string());
+        }
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+async fn stop_debug_task(app: &AppHandle, target: &ExecTarget, reason: &str) {
+    let result = ecs_api::client(&target.profile, &target.region)
+        .await
+        .stop_task()
+        .cluster(&target.cluster)
+        .task(&target.task)
+        .reason(reason)
+        .send()
+        .await;
+    if let Err(e) = result {
+        eprintln!(
+            "[DEBUG] Failed to stop debug task {}: {}",
+            target.task,
+            DisplayErrorContext(e)
+        );
+    }
+    audit::record(
+        app,
+        "stop_debug_task",
+        json!({ "task": target.task, "reason": reason }),
+    );
+}
+
+// Called when a terminal session ends, stops the debug task attached to it
+pub(crate) fn session_closed(session_id: &str) {
+    let closed = DEBUG_TASKS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|t| t.remove(session_id));
+    if let Some(closed) = closed {
+        let _ = closed.send(());
+    }
+}
+
+// Stop the task when its session closes or the TTL passes, whichever is first
+fn supervise(
+    window: Window,
+    session_id: String,
+    target: ExecTarget,
+    ttl: Duration,
+    closed_rx: oneshot::Receiver<()>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let reason = tokio::select! {
+            _ = closed_rx => "Debug session closed",
+            _ = tokio::time::sleep(ttl) => {
+                if let Some(ref mut tasks) = *DEBUG_TASKS.lock().unwrap() {
+                    tasks.remove(&session_id);
+                }
+                let _ = terminal::close_exec_session(session_id.clone()).await;
+                "Debug task TTL expired"
+            }
+        };
+
+        stop_debug_task(window.app_handle(), &target, reason).await;
+        let _ = window.emit(&format!("debug-task:stopped:{}", session_id), reason);
+    });
+}
+
+// Launch a one-off copy of a service's task with its command replaced by a
+// sleep, wait until ECS Exec is ready in it and open a session. The task is
+// stopped when the session closes or after ttl_secs. Containers with an
+// entryPoint in the task definition are refused. An ENTRYPOINT baked into the
+// image cannot be seen from here, it receives "sleep N" as its arguments.
+// Other essential containers start with their real commands, so each has to
+// be named in acknowledged_containers before the task is launched.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn launch_debug_task(
+    window: Window,
+    session_id: String,
+    profile: String,
+    region: String,
+    cluster: String,
+    service: String,
+    container: Option<String>,
+    ttl_secs: Option<u64>,
+    shell_cmd: Option<String>,
+    transport: Option<SessionTransport>,
+    acknowledged_containers: Option<Vec<String>>,
+) -> Result<DebugTask, String> {
+    let ttl_secs = ttl_secs.unwrap_or(DEFAULT_TTL_SECS).clamp(60, MAX_TTL_SECS);
+
+    let details = describe_service(&profile, &region, &cluster, &service).await?;
+    let task_definition = details
+        .task_definition()
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("Service {} has no task definition", service))?;
+    let td = describe_task_definition(&profile, &region, &task_definition).await?;
+    let spec = match container {
+        Some(name) => td
+            .containers
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| format!("Container {} is not in {}", name, task_definition))?,
+        None => td
+            .containers
+            .iter()
+            .find(|c| c.essential)
+            .or(td.containers.first())
+            .ok_or("The task definition has no containers")?,
+    };
+    // Overrides can replace the command but not the entry point, which would
+    // get "sleep" as arguments and start the real process instead
+    if !spec.entry_point.is_empty() {
+        return Err(format!(
+            "Container {} sets an entryPoint ({}), a debug task cannot replace it with sleep",
+            spec.name,
+            spec.entry_point.join(" ")
+        ));
+    }
+    let container = spec.name.clone();
+
+    // Workers or queue consumers next to it would touch live traffic
+    let acknowledged = acknowledged_containers.unwrap_or_default();
+    let unacknowledged: Vec<&str> = td
+        .containers
+        .iter()
+        .filter(|c| c.essential && c.name != container && !acknowledged.contains(&c.name))
+        .map(|c| c.name.as_str())
+        .collect();
+    if !unacknowledged.is_empty() {
+        return Err(format!(
+            "{} would also start with the real command, acknowledge to launch anyway",
+            unacknowledged.join(", ")
+        ));
+    }
+
+    emit_progress(
+        &window,
+        &session_id,
+        format!("Starting a debug task from {}", task_definition),
+    );
+    let task = run_debug_task(
+        &profile,
+        &region,
+        &cluster,
+        &details,
+        &container,
+        &task_definition,
+        ttl_secs + SLEEP_GRACE_SECS,
+    )
+    .await?;
+    audit::record(
+        window.app_handle(),
+        "launch_debug_task",
+        json!({
+            "cluster": cluster,
+            "service": service,
+            "task": task,
+            "container": container,
+            "acknowledgedContainers": acknowledged,
+            "ttlSecs": ttl_secs,
+        }),
+    );
+
+    let target = ExecTarget {
+        profile,
+        region,
+        cluster,
+        task,
+        container: container.clone(),
+    };
+    // Registered before the session opens so an immediate exit still stops the task
+    let (closed_tx, closed_rx) = oneshot::channel();
+    DEBUG_TASKS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(session_id.clone(), closed_tx);
+
+    // From here on the task must not outlive a failure
+    let connected = async {
+        wait_until_exec_ready(&window, &session_id, &target).await?;
+        emit_progress(
+            &window,
+            &session_id,
+            format!("Connecting to {}", task_id_from_arn(&target.task)),
+        );
+        terminal::spawn_exec_session(
+            window.clone(),
+            session_id.clone(),
+            target.clone(),
+            shell_cmd,
+            Vec::new(),
+            false,
+            transport.unwrap_or_default(),
+        )
+        .await
+    }
+    .await;
+
+    let session = match connected {
+        Ok(session) => session,
+        Err(e) => {
+            if let Some(ref mut tasks) = *DEBUG_TASKS.lock().unwrap() {
+                tasks.remove(&session_id);
+            }
+            stop_debug_task(
+                window.app_handle(),
+                &target,
+                "Debug session could not be opened",
+            )
+            .await;
+            return Err(e);
+        }
+    };
+
+    supervise(
+        window,
+        session_id,
+        target.clone(),
+        Duration::from_secs(ttl_secs),
+        closed_rx,
+    );
+    Ok(DebugTask {
+        task_arn: target.task,
+        task_definition,
+        container,
+        expires_at: Utc::now().timestamp_millis() + (ttl_secs as i64) * 1000,
+        session,
+    })
+}
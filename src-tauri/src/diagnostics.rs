@@ -0,0 +1,423 @@
+use chrono::Local;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tauri::{command, Emitter, Manager, Window};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::audit;
+use crate::aws::{run_json_async, task_id_from_arn};
+use crate::exec::{run_exec_command, ExecTarget};
+use crate::storage;
+
+const RECIPE_STORE: &str = ".diagnostics.dat";
+const RECIPE_KEY: &str = "recipe";
+const DEFAULT_STEP_TIMEOUT_SECS: u64 = 60;
+const BUNDLES_DIR: &str = "diagnostics";
+const MANIFEST_FILE: &str = "manifest.json";
+const MASK: &str = "****";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsStep {
+    name: String,
+    command: String,
+    // File name inside the archive
+    file: String,
+    // Mask the values of KEY=VALUE lines whose key looks like a secret
+    #[serde(default)]
+    mask_secrets: bool,
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+}
+
+fn step(name: &str, command: &str, file: &str, mask_secrets: bool) -> DiagnosticsStep {
+    DiagnosticsStep {
+        name: name.to_string(),
+        command: command.to_string(),
+        file: file.to_string(),
+        mask_secrets,
+        timeout_secs: None,
+    }
+}
+
+// Used until the user saves a recipe of their own. Each command falls back to
+// something busybox images have when the usual tool is missing.
+fn default_recipe() -> Vec<DiagnosticsStep> {
+    vec![
+        step("Environment", "env | sort", "env.txt", true),
+        step("Disk usage", "df -h 2>/dev/null || df", "df.txt", false),
+        step(
+            "Memory",
+            "free -m 2>/dev/null; cat /proc/meminfo",
+            "memory.txt",
+            false,
+        ),
+        step(
+            "Open sockets",
+            "ss -tunap 2>/dev/null || netstat -tunap 2>/dev/null || cat /proc/net/tcp /proc/net/tcp6 /proc/net/udp",
+            "sockets.txt",
+            false,
+        ),
+        step(
+            "Processes",
+            "ps aux 2>/dev/null || ps",
+            "processes.txt",
+            false,
+        ),
+        step(
+            "OS release",
+            "cat /etc/os-release 2>/dev/null; uname -a",
+            "os-release.txt",
+            false,
+        ),
+        step(
+            "Recent app logs",
+            r#"find /var/log /app/log /app/logs /app/storage/logs -type f -name '*.log' 2>/dev/null | head -n 10 | while read -r f; do echo "==> $f <=="; tail -n 200 "$f"; done"#,
+            "app-logs.txt",
+            true,
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepResult {
+    name: String,
+    file: String,
+    exit_code: Option<i32>,
+    duration_ms: u64,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsManifest {
+    collected_at: String,
+    profile: String,
+    region: String,
+    cluster: String,
+    task_arn: Option<String>,
+    task_id: String,
+    container: String,
+    image: Option<String>,
+    image_digest: Option<String>,
+    task_definition_arn: Option<String>,
+    task_definition_revision: Option<u64>,
+    launch_type: Option<String>,
+    availability_zone: Option<String>,
+    steps: Vec<StepResult>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsBundle {
+    path: String,
+    manifest: DiagnosticsManifest,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnosticsProgress {
+    task: String,
+    step: String,
+    index: usize,
+    total: usize,
+}
+
+// Hide values that look sensitive: KEY=VALUE lines with a secret-like key and
+// passwords embedded in connection URLs
+fn mask_secrets(output: &str) -> String {
+    let key_pattern =
+        Regex::new(r"(?i)(secret|passw|token|credential|private|api_?key|access_?key|auth|dsn)")
+            .unwrap();
+    let url_password = Regex::new(r"(://[^:/@\s]+:)[^@\s]+@").unwrap();
+
+    output
+        .lines()
+        .map(|line| {
+            let line = match line.split_once('=') {
+                Some((key, value))
+                    if !value.is_empty()
+                        && !key.contains(char::is_whitespace)
+                        && key_pattern.is_match(key) =>
+                {
+                    format!("{}={}", key, MASK)
+                }
+                _ => line.to_string(),
+            };
+            url_password
+                .replace_all(&line, format!("${{1}}{}@", MASK))
+                .into_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Pull the task and container facts recorded in the manifest
+async fn describe_target(target: &ExecTarget) -> Result<Value, String> {
+    let v = run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-tasks",
+            "--cluster",
+            &target.cluster,
+            "--tasks",
+            &target.task,
+            "--region",
+            &target.region,
+            "--profile",
+            &target.profile,
+            "--output",
+            "json",
+        ],
+    )
+    .await?;
+    let task = v["tasks"][0].clone();
+    if task.is_null() {
+        return Err("Task not found".to_string());
+    }
+    Ok(task)
+}
+
+fn revision_from_arn(task_definition_arn: &str) -> Option<u64> {
+    task_definition_arn.rsplit(':').next()?.parse().ok()
+}
+
+fn write_archive(
+    path: &std::path::Path,
+    manifest: &DiagnosticsManifest,
+    files: &[(String, String)],
+) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let manifest = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    let entries = std::iter::once((MANIFEST_FILE, manifest.as_str())).chain(
+        files
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_str())),
+    );
+    for (name, content) in entries {
+        zip.start_file(name, options)
+            .map_err(|e| format!("Failed to add {} to archive: {}", name, e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write {} to archive: {}", name, e))?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(())
+}
+
+#[command]
+pub fn get_diagnostics_recipe(window: Window) -> Result<Vec<DiagnosticsStep>, String> {
+    let recipe: Vec<DiagnosticsStep> =
+        storage::load(window.app_handle(), RECIPE_STORE, RECIPE_KEY)?;
+    if recipe.is_empty() {
+        return Ok(default_recipe());
+    }
+    Ok(recipe)
+}
+
+// Each step's output becomes a file at the top of the archive
+fn validate_recipe(steps: &[DiagnosticsStep]) -> Result<(), String> {
+    let mut files = std::collections::HashSet::new();
+    for step in steps {
+        let file = step.file.trim();
+        if file.is_empty()
+            || file == "."
+            || file == ".."
+            || file.contains(['/', '\\'])
+            || file == MANIFEST_FILE
+        {
+            return Err(format!("Invalid file name for step '{}'", step.name));
+        }
+        if !files.insert(step.file.as_str()) {
+            return Err(format!(
+                "File name '{}' is used by more than one step",
+                step.file
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Saving an empty recipe restores the default one
+#[command]
+pub fn save_diagnostics_recipe(window: Window, steps: Vec<DiagnosticsStep>) -> Result<(), String> {
+    validate_recipe(&steps)?;
+    storage::save(window.app_handle(), RECIPE_STORE, RECIPE_KEY, &steps)
+}
+
+// Run the recipe in the container and save the outputs as a zip archive next to
+// a manifest describing where they came from
+#[command]
+pub async fn collect_diagnostics(
+    window: Window,
+    target: ExecTarget,
+    steps: Option<Vec<DiagnosticsStep>>,
+) -> Result<DiagnosticsBundle, String> {
+    let steps = match steps {
+        Some(steps) => steps,
+        None => get_diagnostics_recipe(window.clone())?,
+    };
+    // Fail before any step runs rather than when the archive is written
+    validate_recipe(&steps)?;
+    let collected_at = Local::now();
+
+    let task = match describe_target(&target).await {
+        Ok(task) => task,
+        Err(e) => {
+            eprintln!(
+                "[DEBUG] Diagnostics could not describe task {}: {}",
+                target.task, e
+            );
+            Value::Null
+        }
+    };
+    let container = task["containers"]
+        .as_array()
+        .and_then(|containers| {
+            containers
+                .iter()
+                .find(|c| c["name"].as_str() == Some(target.container.as_str()))
+        })
+        .cloned()
+        .unwrap_or(Value::Null);
+    let text = |v: &Value| v.as_str().map(|s| s.to_string());
+
+    let mut results = Vec::new();
+    let mut files = Vec::new();
+    for (index, step) in steps.iter().enumerate() {
+        let _ = window.emit(
+            "diagnostics:progress",
+            DiagnosticsProgress {
+                task: target.task.clone(),
+                step: step.name.clone(),
+                index,
+                total: steps.len(),
+            },
+        );
+
+        let timeout = Duration::from_secs(step.timeout_secs.unwrap_or(DEFAULT_STEP_TIMEOUT_SECS));
+        let timer = Instant::now();
+        let (content, exit_code, error) =
+            match run_exec_command(&target, &step.command, timeout).await {
+                Ok(out) => {
+                    let output = if step.mask_secrets {
+                        mask_secrets(&out.output)
+                    } else {
+                        out.output
+                    };
+                    (output, out.exit_code, None)
+                }
+                Err(e) => (format!("Failed to collect: {}\n", e), None, Some(e)),
+            };
+
+        files.push((step.file.clone(), content));
+        results.push(StepResult {
+            name: step.name.clone(),
+            file: step.file.clone(),
+            exit_code,
+            duration_ms: timer.elapsed().as_millis() as u64,
+            error,
+        });
+    }
+
+    let task_definition_arn = text(&task["taskDefinitionArn"]);
+    let manifest = DiagnosticsManifest {
+        collected_at: collected_at.to_rfc3339(),
+        profile: target.profile.clone(),
+        region: target.region.clone(),
+        cluster: target.cluster.clone(),
+        task_arn: text(&task["taskArn"]),
+        task_id: task_id_from_arn(&target.task),
+        container: target.container.clone(),
+        image: text(&container["image"]),
+        image_digest: text(&container["imageDigest"]),
+        task_definition_revision: task_definition_arn.as_deref().and_then(revision_from_arn),
+        task_definition_arn,
+        launch_type: text(&task["launchType"]),
+        availability_zone: text(&task["availabilityZone"]),
+        steps: results,
+    };
+
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join(BUNDLES_DIR);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create diagnostics directory: {}", e))?;
+    let path = dir.join(format!(
+        "diagnostics-{}-{}.zip",
+        manifest.task_id,
+        collected_at.format("%Y%m%d-%H%M%S")
+    ));
+    write_archive(&path, &manifest, &files)?;
+
+    audit::record(
+        window.app_handle(),
+        "collect_diagnostics",
+        json!({
+            "task": target.task,
+            "container": target.container,
+            "path": path.to_string_lossy(),
+        }),
+    );
+
+    Ok(DiagnosticsBundle {
+        path: path.to_string_lossy().into_owned(),
+        manifest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_recipe_is_valid() {
+        assert!(validate_recipe(&default_recipe()).is_ok());
+    }
+
+    #[test]
+    fn validate_recipe_rejects_unsafe_and_duplicate_names() {
+        for file in [
+            "",
+            " ",
+            ".",
+            "..",
+            "../escape.txt",
+            "logs\\app.txt",
+            MANIFEST_FILE,
+        ] {
+            let steps = vec![step("env", "env", file, true)];
+            assert!(validate_recipe(&steps).is_err(), "{:?} was accepted", file);
+        }
+        let steps = vec![
+            step("env", "env", "env.txt", true),
+            step("env again", "printenv", "env.txt", true),
+        ];
+        assert!(validate_recipe(&steps)
+            .unwrap_err()
+            .contains("more than one step"));
+    }
+
+    #[test]
+    fn mask_secrets_hides_secret_values_and_url_passwords() {
+        let output = "PATH=/usr/bin\nDB_PASSWORD=hunter2\nAPI_KEY=abc\nDATABASE_URL=postgres://app:s3cret@db:5432/app\nnot a key=value";
+        assert_eq!(
+            mask_secrets(output),
+            "PATH=/usr/bin\nDB_PASSWORD=****\nAPI_KEY=****\nDATABASE_URL=postgres://app:****@db:5432/app\nnot a key=value"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use aws_config::{BehaviorVersion, SdkConfig};
+use aws_sdk_ecs::config::Region;
+use aws_sdk_ecs::error::DisplayErrorContext;
+use aws_sdk_ecs::types::{Cluster, ClusterField};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::exec::ExecTarget;
+
+// Loading a profile resolves credentials and is slow, keep one config per profile and region
+static CONFIGS: Mutex<Option<HashMap<(String, String), SdkConfig>>> = Mutex::new(None);
+
+// Session Manager details returned by ExecuteCommand
+#[derive(Debug, Clone)]
+pub(crate) struct ExecSession {
+    pub session_id: String,
+    pub stream_url: String,
+    pub token_value: String,
+    pub cluster_arn: Option<String>,
+    pub task_arn: Option<String>,
+    pub container_name: Option<String>,
+}
+
+// A cluster's ECS Exec settings, from its executeCommandConfiguration
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecConfiguration {
+    // Session data is encrypted with this key on top of TLS
+    pub kms_key_id: Option<String>,
+    // NONE, DEFAULT or OVERRIDE
+    pub logging: Option<String>,
+    pub cloud_watch_log_group: Option<String>,
+    pub cloud_watch_encryption_enabled: bool,
+    pub s3_bucket: Option<String>,
+    pub s3_key_prefix: Option<String>,
+    pub s3_encryption_enabled: bool,
+}
+
+// SDK configuration for a profile and region, using the same credential
+// sources as the AWS CLI (config files, SSO cache, credential processes)
+pub(crate) async fn sdk_config(profile: &str, region: &str) -> SdkConfig {
+    let key = (profile.to_string(), region.to_string());
+    if let Some(config) = CONFIGS.lock().unwrap().as_ref().and_then(|c| c.get(&key)) {
+        return config.clone();
+    }
+
+    let config = aws_config::defaults(BehaviorVersion::latest())
+        .profile_name(profile)
+        .region(Region::new(region.to_string()))
+        .load()
+        .await;
+
+    CONFIGS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(key, config.clone());
+    config
+}
+
+pub(crate) async fn client(profile: &str, region: &str) -> aws_sdk_ecs::Client {
+    aws_sdk_ecs::Client::new(&sdk_config(profile, region).await)
+}
+
+// Call ExecuteCommand and return the session to attach to
+pub(crate) async fn execute_command(
+    target: &ExecTarget,
+    command: &str,
+    interactive: bool,
+) -> Result<ExecSession, String> {
+    let out = client(&target.profile, &target.region)
+        .await
+        .execute_command()
+        .cluster(&target.cluster)
+        .task(&target.task)
+        .container(&target.container)
+        .command(command)
+        .interactive(interactive)
+        .send()
+        .await
+        .map_err(|e| format!("ExecuteCommand failed: {}", DisplayErrorContext(e)))?;
+
+    let session = out.session().ok_or("ExecuteCommand returned no session")?;
+    let field = |value: Option<&str>, name: &str| {
+        value
+            .map(|s| s.to_string())
+            .ok_or(format!("ExecuteCommand session has no {}", name))
+    };
+
+    Ok(ExecSession {
+        session_id: field(session.session_id(), "session id")?,
+        stream_url: field(session.stream_url(), "stream URL")?,
+        token_value: field(session.token_value(), "token")?,
+        cluster_arn: out.cluster_arn().map(|s| s.to_string()),
+        task_arn: out.task_arn().map(|s| s.to_string()),
+        container_name: out.container_name().map(|s| s.to_string()),
+    })
+}
+
+// The runtime id of the target container, which Session Manager addresses it by
+pub(crate) async fn container_runtime_id(target: &ExecTarget) -> Result<String, String> {
+    let out = client(&target.profile, &target.region)
+        .await
+        .describe_tasks()
+        .cluster(&target.cluster)
+        .tasks(&target.task)
+        .send()
+        .await
+        .map_err(|e| format!("DescribeTasks failed: {}", DisplayErrorContext(e)))?;
+
+    let task = out.tasks().first().ok_or("Task not found")?;
+    task.containers()
+        .iter()
+        .find(|c| c.name() == Some(target.container.as_str()))
+        .and_then(|c| c.runtime_id())
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("Container {} has no runtime id yet", target.container))
+}
+
+pub(crate) fn exec_configuration_of(cluster: &Cluster) -> ExecConfiguration {
+    let Some(config) = cluster
+        .configuration()
+        .and_then(|c| c.execute_command_configuration())
+    else {
+        return ExecConfiguration::default();
+    };
+    let log = config.log_configuration();
+
+    ExecConfiguration {
+        kms_key_id: config.kms_key_id().map(|s| s.to_string()),
+        logging: config.logging().map(|l| l.as_str().to_string()),
+        cloud_watch_log_group: log
+            .and_then(|l| l.cloud_watch_log_group_name())
+            .map(|s| s.to_string()),
+        cloud_watch_encryption_enabled: log.is_some_and(|l| l.cloud_watch_encryption_enabled()),
+        s3_bucket: log.and_then(|l| l.s3_bucket_name()).map(|s| s.to_string()),
+        s3_key_prefix: log.and_then(|l| l.s3_key_prefix()).map(|s| s.to_string()),
+        s3_encryption_enabled: log.is_some_and(|l| l.s3_encryption_enabled()),
+    }
+}
+
+pub(crate) async fn exec_configuration(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+) -> Result<ExecConfiguration, String> {
+    let out = client(profile, region)
+        .await
+        .describe_clusters()
+        .clusters(cluster)
+        .include(ClusterField::Configurations)
+        .send()
+        .await
+        .map_err(|e| format!("DescribeClusters failed: {}", DisplayErrorContext(e)))?;
+
+    Ok(out
+        .clusters()
+        .first()
+        .map(exec_configuration_of)
+        .unwrap_or_default())
+}
+
+// The ECS endpoint session-manager-plugin reports the session against
+pub(crate) fn ecs_endpoint(region: &str) -> String {
+    let suffix = if region.starts_with("cn-") {
+        "amazonaws.com.cn"
+    } else {
+        "amazonaws.com"
+    };
+    format!("https://ecs.{}.{}", region, suffix)
+}
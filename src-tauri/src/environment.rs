@@ -0,0 +1,256 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{command, Manager, Window};
+
+use crate::audit;
+use crate::ecs_api;
+use crate::exec::{run_exec_command, ExecTarget};
+use crate::task_definitions::{describe_task_definition, EnvironmentFile};
+
+const MASK: &str = "****";
+const LIVE_ENV_TIMEOUT_SECS: u64 = 30;
+
+// The environment of the container's main process, NUL separated so values
+// with newlines survive. Falls back to the exec shell's own environment.
+const LIVE_ENV_SCRIPT: &str =
+    "if [ -r /proc/1/environ ]; then base64 < /proc/1/environ; else env -0 | base64; fi";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EnvSource {
+    Environment,
+    Secret,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvEntry {
+    name: String,
+    source: EnvSource,
+    // None for secrets, their values are never read
+    value: Option<String>,
+    // ARN of the Secrets Manager secret or SSM parameter
+    value_from: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerEnvironment {
+    task_definition: String,
+    container: String,
+    variables: Vec<EnvEntry>,
+    // Loaded by ECS at start, their variables only show up in the live environment
+    environment_files: Vec<EnvironmentFile>,
+    masked: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EnvStatus {
+    Same,
+    Different,
+    // Declared but not set in the container
+    MissingLive,
+    // Set in the container only: from an environment file, the image or the runtime
+    Undeclared,
+    // A secret reference that is set in the container
+    SecretInjected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvComparison {
+    name: String,
+    source: Option<EnvSource>,
+    declared: Option<String>,
+    live: Option<String>,
+    value_from: Option<String>,
+    status: EnvStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveEnvironment {
+    declared: ContainerEnvironment,
+    entries: Vec<EnvComparison>,
+}
+
+async fn task_definition_arn(target: &ExecTarget) -> Result<String, String> {
+    let out = ecs_api::client(&target.profile, &target.region)
+        .await
+        .describe_tasks()
+        .cluster(&target.cluster)
+        .tasks(&target.task)
+        .send()
+        .await
+        .map_err(|e| format!("DescribeTasks failed: {}", DisplayErrorContext(e)))?;
+    out.tasks()
+        .first()
+        .and_then(|t| t.task_definition_arn())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Task not found".to_string())
+}
+
+fn mask(value: &str, masked: bool) -> String {
+    if masked {
+        MASK.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// The declared environment with real values, masking happens on the way out
+async fn declared_environment(target: &ExecTarget) -> Result<ContainerEnvironment, String> {
+    let arn = task_definition_arn(target).await?;
+    let td = describe_task_definition(&target.profile, &target.region, &arn).await?;
+    let container = td
+        .containers
+        .into_iter()
+        .find(|c| c.name == target.container)
+        .ok_or_else(|| format!("Container {} is not in {}", target.container, arn))?;
+
+    let mut variables: Vec<EnvEntry> = container
+        .environment
+        .into_iter()
+        .map(|e| EnvEntry {
+            name: e.name,
+            source: EnvSource::Environment,
+            value: Some(e.value),
+            value_from: None,
+        })
+        .chain(container.secrets.into_iter().map(|s| EnvEntry {
+            name: s.name,
+            source: EnvSource::Secret,
+            value: None,
+            value_from: Some(s.value_from),
+        }))
+        .collect();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(ContainerEnvironment {
+        task_definition: arn,
+        container: target.container.clone(),
+        variables,
+        environment_files: container.environment_files,
+        masked: false,
+    })
+}
+
+fn masked_environment(mut env: ContainerEnvironment, masked: bool) -> ContainerEnvironment {
+    for variable in &mut env.variables {
+        variable.value = variable.value.as_deref().map(|v| mask(v, masked));
+    }
+    env.masked = masked;
+    env
+}
+
+async fn live_environment(target: &ExecTarget) -> Result<BTreeMap<String, String>, String> {
+    let out = run_exec_command(
+        target,
+        LIVE_ENV_SCRIPT,
+        Duration::from_secs(LIVE_ENV_TIMEOUT_SECS),
+    )
+    .await?;
+    if out.exit_code != Some(0) {
+        return Err(format!(
+            "Failed to read the container environment: {}",
+            out.output.trim()
+        ));
+    }
+
+    let encoded: String = out.output.split_whitespace().collect();
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Failed to decode the container environment: {}", e))?;
+    Ok(data
+        .split(|b| *b == 0)
+        .filter_map(|entry| {
+            let entry = String::from_utf8_lossy(entry);
+            let (name, value) = entry.split_once('=')?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect())
+}
+
+// Environment, environment files and secret references a container is
+// declared with. Values are masked unless reveal is set.
+#[command]
+pub async fn get_container_environment(
+    window: Window,
+    target: ExecTarget,
+    reveal: Option<bool>,
+) -> Result<ContainerEnvironment, String> {
+    let reveal = reveal.unwrap_or(false);
+    let env = declared_environment(&target).await?;
+    if reveal {
+        audit::record(
+            window.app_handle(),
+            "reveal_container_environment",
+            json!({ "task": target.task, "container": target.container }),
+        );
+    }
+    Ok(masked_environment(env, !reveal))
+}
+
+// Read the running container's environment over exec and compare it with
+// the declared one. Values are masked unless reveal is set, the comparison
+// itself always uses the real values.
+#[command]
+pub async fn compare_live_environment(
+    window: Window,
+    target: ExecTarget,
+    reveal: Option<bool>,
+) -> Result<LiveEnvironment, String> {
+    let reveal = reveal.unwrap_or(false);
+    let (declared, live) = tokio::join!(declared_environment(&target), live_environment(&target));
+    let (declared, mut live) = (declared?, live?);
+
+    audit::record(
+        window.app_handle(),
+        "read_live_environment",
+        json!({ "task": target.task, "container": target.container, "revealed": reveal }),
+    );
+
+    let mut entries: Vec<EnvComparison> = declared
+        .variables
+        .iter()
+        .map(|variable| {
+            let live_value = live.remove(&variable.name);
+            let status = match (variable.source, &live_value) {
+                (_, None) => EnvStatus::MissingLive,
+                (EnvSource::Secret, Some(_)) => EnvStatus::SecretInjected,
+                (EnvSource::Environment, Some(value)) if variable.value.as_ref() == Some(value) => {
+                    EnvStatus::Same
+                }
+                (EnvSource::Environment, Some(_)) => EnvStatus::Different,
+            };
+            EnvComparison {
+                name: variable.name.clone(),
+                source: Some(variable.source),
+                declared: variable.value.as_deref().map(|v| mask(v, !reveal)),
+                live: live_value.map(|v| mask(&v, !reveal)),
+                value_from: variable.value_from.clone(),
+                status,
+            }
+        })
+        .collect();
+
+    entries.extend(live.into_iter().map(|(name, value)| EnvComparison {
+        name,
+        source: None,
+        declared: None,
+        live: Some(mask(&value, !reveal)),
+        value_from: None,
+        status: EnvStatus::Undeclared,
+    }));
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(LiveEnvironment {
+        declared: masked_environment(declared, !reveal),
+        entries,
+    })
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::command;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command as TokioCommand;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::aws::{get_path_with_common_locations, list_running_tasks, task_id_from_arn};
+
+// Printed after the user's command so we can recover its exit status,
+// ECS Exec itself does not report one back
+const EXIT_MARKER: &str = "__ECS_EXEC_EXIT__";
+
+const DEFAULT_CONCURRENCY: usize = 5;
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecTarget {
+    pub profile: String,
+    pub region: String,
+    pub cluster: String,
+    pub task: String,
+    pub container: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExecOutput {
+    pub output: String,
+    pub exit_code: Option<i32>,
+}
+
+// Quote a string for use as a single POSIX shell word
+pub(crate) fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Run a command inside a container without a terminal attached and collect its output
+pub(crate) async fn run_exec_command(
+    target: &ExecTarget,
+    command: &str,
+    timeout: Duration,
+) -> Result<ExecOutput, String> {
+    let wrapped = format!(
+        "sh -c {}",
+        shell_quote(&format!("{}; echo \"{}$?\"", command, EXIT_MARKER))
+    );
+    run_exec_direct(target, &wrapped, timeout).await
+}
+
+// Run a command line as-is, without wrapping it in a shell. The exit code is
+// only known when the command echoes the exit marker itself.
+pub(crate) async fn run_exec_direct(
+    target: &ExecTarget,
+    command_line: &str,
+    timeout: Duration,
+) -> Result<ExecOutput, String> {
+    let path = get_path_with_common_locations();
+
+    let mut child = TokioCommand::new("aws")
+        .env("PATH", &path)
+        .arg("ecs")
+        .arg("execute-command")
+        .arg("--cluster")
+        .arg(&target.cluster)
+        .arg("--task")
+        .arg(&target.task)
+        .arg("--container")
+        .arg(&target.container)
+        .arg("--command")
+        .arg(command_line)
+        .arg("--interactive")
+        .arg("--region")
+        .arg(&target.region)
+        .arg("--profile")
+        .arg(&target.profile)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn process: {}", e))?;
+
+    // Keep stdin open until the command finishes, the plugin ends the session on EOF
+    let _stdin = child.stdin.take();
+    let mut stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+    let mut stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+
+    let result = tokio::time::timeout(timeout, async {
+        let mut stdout_buf = Vec::new();
+        let mut stderr_buf = Vec::new();
+        let (out, err) = tokio::join!(
+            stdout.read_to_end(&mut stdout_buf),
+            stderr.read_to_end(&mut stderr_buf)
+        );
+        out.and(err).map_err(|e| format!("Failed to read output: {}", e))?;
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| format!("Failed to wait for command: {}", e))?;
+        Ok::<_, String>((status, stdout_buf, stderr_buf))
+    })
+    .await
+    .map_err(|_| format!("Command timeout after {} seconds", timeout.as_secs()))??;
+
+    let (status, stdout_buf, stderr_buf) = result;
+    let (output, exit_code) = parse_exec_output(&String::from_utf8_lossy(&stdout_buf));
+
+    if exit_code.is_none() && !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr_buf);
+        return Err(format!("ECS Exec failed: {}", stderr.trim()));
+    }
+
+    Ok(ExecOutput { output, exit_code })
+}
+
+// Strip Session Manager banners and the exit marker from raw exec output
+fn parse_exec_output(raw: &str) -> (String, Option<i32>) {
+    let normalized = raw.replace("\r\n", "\n");
+    let mut exit_code = None;
+    let mut lines = Vec::new();
+
+    for line in normalized.lines() {
+        if line.starts_with("Starting session with SessionId:")
+            || line.starts_with("Exiting session with sessionId:")
+        {
+            continue;
+        }
+        if let Some(code) = line.trim().strip_prefix(EXIT_MARKER) {
+            exit_code = code.trim().parse().ok();
+            continue;
+        }
+        lines.push(line);
+    }
+
+    let output = lines.join("\n").trim_matches('\n').to_string();
+    (output, exit_code)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskExecResult {
+    task_arn: String,
+    task_id: String,
+    output: Option<String>,
+    exit_code: Option<i32>,
+    error: Option<String>,
+    differs: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputGroup {
+    output: String,
+    exit_code: Option<i32>,
+    task_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutResult {
+    results: Vec<TaskExecResult>,
+    groups: Vec<OutputGroup>,
+}
+
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn exec_fan_out(
+    profile: String,
+    region: String,
+    cluster: String,
+    service: String,
+    container: String,
+    command: String,
+    concurrency: Option<usize>,
+    timeout_secs: Option<u64>,
+) -> Result<FanOutResult, String> {
+    let task_arns = list_running_tasks(&profile, &region, &cluster, Some(&service)).await?;
+
+    if task_arns.is_empty() {
+        return Err("No running tasks found for this service".to_string());
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1)));
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let command = Arc::new(command);
+    let mut set = JoinSet::new();
+
+    for (index, task_arn) in task_arns.iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let command = command.clone();
+        let target = ExecTarget {
+            profile: profile.clone(),
+            region: region.clone(),
+            cluster: cluster.clone(),
+            task: task_arn.clone(),
+            container: container.clone(),
+        };
+        set.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            (index, run_exec_command(&target, &command, timeout).await)
+        });
+    }
+
+    let mut outcomes: Vec<Option<Result<ExecOutput, String>>> = task_arns.iter().map(|_| None).collect();
+    while let Some(joined) = set.join_next().await {
+        let (index, outcome) = joined.map_err(|e| format!("Exec task failed: {}", e))?;
+        outcomes[index] = Some(outcome);
+    }
+
+    let mut results: Vec<TaskExecResult> = task_arns
+        .into_iter()
+        .zip(outcomes)
+        .map(|(task_arn, outcome)| {
+            let task_id = task_id_from_arn(&task_arn);
+            let (output, exit_code, error) = match outcome {
+                Some(Ok(out)) => (Some(out.output), out.exit_code, None),
+                Some(Err(e)) => (None, None, Some(e)),
+                None => (None, None, Some("Exec did not complete".to_string())),
+            };
+            TaskExecResult {
+                task_arn,
+                task_id,
+                output,
+                exit_code,
+                error,
+                differs: false,
+            }
+        })
+        .collect();
+
+    let groups = group_outputs(&results);
+
+    // Anything outside the largest group is highlighted as differing
+    if groups.len() > 1 || results.iter().any(|r| r.error.is_some()) {
+        if let Some(majority) = groups.first() {
+            for result in results.iter_mut() {
+                result.differs = !majority.task_ids.contains(&result.task_id);
+            }
+        }
+    }
+
+    Ok(FanOutResult { results, groups })
+}
+
+// Group successful results by identical output and exit status, largest group first
+fn group_outputs(results: &[TaskExecResult]) -> Vec<OutputGroup> {
+    let mut grouped: HashMap<(String, Option<i32>), Vec<String>> = HashMap::new();
+    for result in results {
+        if let Some(ref output) = result.output {
+            grouped
+                .entry((output.clone(), result.exit_code))
+                .or_default()
+                .push(result.task_id.clone());
+        }
+    }
+
+    let mut groups: Vec<OutputGroup> = grouped
+        .into_iter()
+        .map(|((output, exit_code), task_ids)| OutputGroup {
+            output,
+            exit_code,
+            task_ids,
+        })
+        .collect();
+    groups.sort_by(|a, b| b.task_ids.len().cmp(&a.task_ids.len()).then_with(|| a.task_ids.cmp(&b.task_ids)));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(task_id: &str, output: Option<&str>, exit_code: Option<i32>) -> TaskExecResult {
+        TaskExecResult {
+            task_arn: format!("arn:aws:ecs:eu-west-1:123456789012:task/main/{}", task_id),
+            task_id: task_id.to_string(),
+            output: output.map(|s| s.to_string()),
+            exit_code,
+            error: output
+                .is_none()
+                .then(|| "ExecuteCommand failed".to_string()),
+            differs: false,
+        }
+    }
+
+    #[test]
+    fn parse_exec_output_strips_banners_and_exit_marker() {
+        let raw = "\r\nStarting session with SessionId: ecs-execute-command-0abc\r\nhello\r\nworld\r\n__ECS_EXEC_EXIT__2\r\n\r\nExiting session with sessionId: ecs-execute-command-0abc.\r\n";
+        assert_eq!(
+            parse_exec_output(raw),
+            ("hello\nworld".to_string(), Some(2))
+        );
+    }
+
+    #[test]
+    fn parse_exec_output_without_marker_has_no_exit_code() {
+        assert_eq!(
+            parse_exec_output("partial output\n"),
+            ("partial output".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn group_outputs_groups_identical_results_largest_first() {
+        let results = vec![
+            result("c", Some("v2"), Some(0)),
+            result("a", Some("v1"), Some(0)),
+            result("b", Some("v1"), Some(0)),
+            result("d", Some("v1"), Some(1)),
+            result("e", None, None),
+        ];
+        let groups = group_outputs(&results);
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].output, "v1");
+        assert_eq!(groups[0].exit_code, Some(0));
+        assert_eq!(groups[0].task_ids, vec!["a", "b"]);
+        // Ties are ordered by task id so the result is stable
+        assert_eq!(groups[1].task_ids, vec!["c"]);
+        assert_eq!(groups[2].task_ids, vec!["d"]);
+        assert_eq!(groups[2].exit_code, Some(1));
+    }
+}
@@ -0,0 +1,512 @@
+use base64::Engine;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, Manager, Window};
+
+use crate::exec::{run_exec_command, shell_quote, ExecTarget};
+
+const LIST_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 5000;
+const TRANSFER_TIMEOUT_SECS: u64 = 120;
+// Files are moved as base64 on the command line, keep edits to config-sized files
+const MAX_EDIT_BYTES: usize = 1024 * 1024;
+// Raw bytes per upload command, a multiple of 3 so every chunk decodes on its own
+const UPLOAD_CHUNK_BYTES: usize = 48 * 1024;
+
+// Field separator for listing output (printf \037), unlikely to appear in file names
+const FIELD_SEP: char = '\x1f';
+const PWD_MARKER: &str = "__ECS_PWD__";
+const TOTAL_MARKER: &str = "__ECS_TOTAL__";
+const ENTRY_MARKER: &str = "__ECS_ENTRY__";
+// Printed with the size instead of the contents of a file over MAX_EDIT_BYTES
+const TOO_LARGE_MARKER: &str = "__ECS_TOO_LARGE__";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteEntry {
+    name: String,
+    path: String,
+    kind: EntryKind,
+    size: Option<u64>,
+    // Octal permission bits, e.g. "0755"
+    mode: Option<String>,
+    // ls-style permission string, e.g. "drwxr-xr-x"
+    permissions: Option<String>,
+    owner: Option<String>,
+    group: Option<String>,
+    // Seconds since the epoch
+    mtime: Option<i64>,
+    symlink_target: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteDirListing {
+    path: String,
+    entries: Vec<RemoteEntry>,
+    offset: usize,
+    total: usize,
+    has_more: bool,
+}
+
+// Script run through sh in the container. Sticks to POSIX sh plus stat -c and
+// readlink, which GNU coreutils and busybox both provide, and falls back to
+// ls -ld when stat is missing. Only entries in the requested page are stat'ed.
+fn listing_script(path: &str, offset: usize, limit: usize) -> String {
+    format!(
+        r#"cd -- {path} || exit 1
+echo "{pwd}$(pwd)"
+has_stat=0; command -v stat >/dev/null 2>&1 && has_stat=1
+i=0
+for f in .[!.]* ..?* *; do
+  [ -e "$f" ] || [ -L "$f" ] || continue
+  i=$((i+1))
+  [ "$i" -le {offset} ] && continue
+  [ "$i" -gt {end} ] && continue
+  t=""
+  [ -L "$f" ] && t=$(readlink -- "$f")
+  if [ "$has_stat" = 1 ]; then
+    s=$(stat -c '%s|%f|%A|%U|%G|%Y' -- "$f" 2>/dev/null) || continue
+  else
+    s="ls|$(ls -ld -- "$f" 2>/dev/null)"
+  fi
+  printf '{entry}%s{sep}%s{sep}%s\n' "$s" "$t" "$f"
+done
+echo "{total}$i""#,
+        path = shell_quote(path),
+        pwd = PWD_MARKER,
+        offset = offset,
+        end = offset.saturating_add(limit),
+        entry = ENTRY_MARKER,
+        sep = "\\037",
+        total = TOTAL_MARKER,
+    )
+}
+
+fn kind_from_mode(mode: u32) -> EntryKind {
+    match mode & 0o170000 {
+        0o040000 => EntryKind::Directory,
+        0o100000 => EntryKind::File,
+        0o120000 => EntryKind::Symlink,
+        _ => EntryKind::Other,
+    }
+}
+
+fn kind_from_permissions(permissions: &str) -> EntryKind {
+    match permissions.chars().next() {
+        Some('d') => EntryKind::Directory,
+        Some('-') => EntryKind::File,
+        Some('l') => EntryKind::Symlink,
+        _ => EntryKind::Other,
+    }
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+// Parse one entry line: stat fields (or an ls -ld line), link target and name
+fn parse_entry(dir: &str, line: &str) -> Option<RemoteEntry> {
+    let mut parts = line.splitn(3, FIELD_SEP);
+    let stat = parts.next()?;
+    let target = parts.next()?;
+    let name = parts.next()?.to_string();
+    let symlink_target = (!target.is_empty()).then(|| target.to_string());
+    let path = join_path(dir, &name);
+
+    if let Some(ls) = stat.strip_prefix("ls|") {
+        // mode links owner group size ...
+        let cols: Vec<&str> = ls.split_whitespace().collect();
+        let permissions = cols.first().map(|s| s.to_string());
+        return Some(RemoteEntry {
+            name,
+            path,
+            kind: permissions
+                .as_deref()
+                .map(kind_from_permissions)
+                .unwrap_or(EntryKind::Other),
+            size: cols.get(4).and_then(|s| s.parse().ok()),
+            mode: None,
+            permissions,
+            owner: cols.get(2).map(|s| s.to_string()),
+            group: cols.get(3).map(|s| s.to_string()),
+            mtime: None,
+            symlink_target,
+        });
+    }
+
+    let fields: Vec<&str> = stat.split('|').collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    let raw_mode = u32::from_str_radix(fields[1], 16).ok()?;
+
+    Some(RemoteEntry {
+        name,
+        path,
+        kind: kind_from_mode(raw_mode),
+        size: fields[0].parse().ok(),
+        mode: Some(format!("{:04o}", raw_mode & 0o7777)),
+        permissions: Some(fields[2].to_string()),
+        owner: Some(fields[3].to_string()),
+        group: Some(fields[4].to_string()),
+        mtime: fields[5].parse().ok(),
+        symlink_target,
+    })
+}
+
+#[command]
+pub async fn list_remote_dir(
+    target: ExecTarget,
+    path: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<RemoteDirListing, String> {
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let out = run_exec_command(
+        &target,
+        &listing_script(&path, offset, limit),
+        Duration::from_secs(LIST_TIMEOUT_SECS),
+    ).await?;
+
+    let mut resolved = None;
+    let mut total = 0;
+    let mut lines = Vec::new();
+    for line in out.output.lines() {
+        if let Some(pwd) = line.strip_prefix(PWD_MARKER) {
+            resolved = Some(pwd.to_string());
+        } else if let Some(count) = line.strip_prefix(TOTAL_MARKER) {
+            total = count.trim().parse().unwrap_or(0);
+        } else if let Some(entry) = line.strip_prefix(ENTRY_MARKER) {
+            lines.push(entry);
+        }
+    }
+
+    let Some(resolved) = resolved else {
+        return Err(format!("Cannot open directory {}: {}", path, out.output.trim()));
+    };
+
+    let entries = lines
+        .into_iter()
+        .filter_map(|line| parse_entry(&resolved, line))
+        .collect();
+
+    Ok(RemoteDirListing {
+        path: resolved,
+        entries,
+        offset,
+        total,
+        has_more: offset.saturating_add(limit) < total,
+    })
+}
+
+struct RemoteEdit {
+    target: ExecTarget,
+    remote_path: String,
+    local_path: PathBuf,
+    original_hash: String,
+}
+
+static REMOTE_EDITS: Mutex<Option<HashMap<String, RemoteEdit>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteFileEdit {
+    edit_id: String,
+    remote_path: String,
+    local_path: String,
+    // File contents for the in-app editor, None when the file is not UTF-8
+    content: Option<String>,
+    hash: String,
+    size: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSaveResult {
+    hash: String,
+    backup_path: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// Fetch a file for editing. Its size is checked in the container first so a
+// large file is refused before any of it is transferred.
+async fn download_remote_file(target: &ExecTarget, path: &str) -> Result<Vec<u8>, String> {
+    // No exit, it would skip the exit marker run_exec_command appends
+    let script = format!(
+        "if [ ! -f {p} ]; then echo 'Not a regular file' >&2; false; \
+         elif size=$(wc -c < {p}); then \
+         if [ $size -le {max} ]; then base64 < {p}; else echo {marker}$size; fi; \
+         else false; fi",
+        p = shell_quote(path),
+        max = MAX_EDIT_BYTES,
+        marker = TOO_LARGE_MARKER
+    );
+    let out = run_exec_command(target, &script, Duration::from_secs(TRANSFER_TIMEOUT_SECS)).await?;
+    if let Some(size) = out
+        .output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(TOO_LARGE_MARKER))
+    {
+        return Err(format!("{} is too large to edit ({} bytes)", path, size));
+    }
+    if out.exit_code != Some(0) {
+        return Err(format!("Failed to read {}: {}", path, out.output.trim()));
+    }
+
+    let encoded: String = out.output.split_whitespace().collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Failed to decode {}: {}", path, e))
+}
+
+// Hash of the remote file as it is now. Without sha256sum in the container the
+// conflict check cannot run, the save has to be forced instead.
+async fn remote_hash(target: &ExecTarget, path: &str) -> Result<String, String> {
+    let script = format!("sha256sum -- {}", shell_quote(path));
+    let out = run_exec_command(target, &script, Duration::from_secs(TRANSFER_TIMEOUT_SECS)).await?;
+    match out.output.split_whitespace().next() {
+        Some(hash) if out.exit_code == Some(0) => Ok(hash.to_lowercase()),
+        _ => Err(format!(
+            "Could not check {} for changes, save with force to overwrite it: {}",
+            path,
+            out.output.trim()
+        )),
+    }
+}
+
+// Write new contents next to the file, then rename over it. The temporary copy
+// starts as cp -p of the original so mode and ownership carry over.
+async fn upload_remote_file(target: &ExecTarget, path: &str, data: &[u8], tag: &str) -> Result<(), String> {
+    let timeout = Duration::from_secs(TRANSFER_TIMEOUT_SECS);
+    let tmp = format!("{}.ecs-edit-{}", path, tag);
+
+    let prepare = format!(
+        "cp -p -- {p} {t} && : > {t}",
+        p = shell_quote(path),
+        t = shell_quote(&tmp)
+    );
+    let out = run_exec_command(target, &prepare, timeout).await?;
+    if out.exit_code != Some(0) {
+        return Err(format!("Failed to prepare {}: {}", tmp, out.output.trim()));
+    }
+
+    for chunk in data.chunks(UPLOAD_CHUNK_BYTES) {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(chunk);
+        let append = format!(
+            "printf '%s' {} | base64 -d >> {}",
+            shell_quote(&encoded),
+            shell_quote(&tmp)
+        );
+        let out = run_exec_command(target, &append, timeout).await?;
+        if out.exit_code != Some(0) {
+            let _ = run_exec_command(target, &format!("rm -f -- {}", shell_quote(&tmp)), timeout).await;
+            return Err(format!("Failed to upload {}: {}", path, out.output.trim()));
+        }
+    }
+
+    let commit = format!("mv -f -- {} {}", shell_quote(&tmp), shell_quote(path));
+    let out = run_exec_command(target, &commit, timeout).await?;
+    if out.exit_code != Some(0) {
+        return Err(format!("Failed to replace {}: {}", path, out.output.trim()));
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn open_remote_file(
+    window: Window,
+    target: ExecTarget,
+    path: String,
+) -> Result<RemoteFileEdit, String> {
+    let data = download_remote_file(&target, &path).await?;
+    // The file may have grown between the size check and the transfer
+    if data.len() > MAX_EDIT_BYTES {
+        return Err(format!("{} is too large to edit ({} bytes)", path, data.len()));
+    }
+
+    let edit_id = uuid::Uuid::new_v4().to_string();
+    let file_name = path.rsplit('/').next().filter(|n| !n.is_empty()).unwrap_or("file");
+    let dir = window
+        .app_handle()
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?
+        .join("remote-edits")
+        .join(&edit_id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    // Keep the downloaded original next to the working copy
+    let local_path = dir.join(file_name);
+    std::fs::write(dir.join(format!("{}.orig", file_name)), &data)
+        .and_then(|_| std::fs::write(&local_path, &data))
+        .map_err(|e| format!("Failed to write local copy: {}", e))?;
+
+    let hash = sha256_hex(&data);
+    let edit = RemoteFileEdit {
+        edit_id: edit_id.clone(),
+        remote_path: path.clone(),
+        local_path: local_path.to_string_lossy().to_string(),
+        content: String::from_utf8(data.clone()).ok(),
+        hash: hash.clone(),
+        size: data.len(),
+    };
+
+    REMOTE_EDITS.lock().unwrap().get_or_insert_with(HashMap::new).insert(
+        edit_id,
+        RemoteEdit {
+            target,
+            remote_path: path,
+            local_path,
+            original_hash: hash,
+        },
+    );
+
+    Ok(edit)
+}
+
+// Open the local copy in the given editor command, $VISUAL/$EDITOR, or the OS default
+#[command]
+pub fn launch_local_editor(edit_id: String, editor: Option<String>) -> Result<(), String> {
+    let local_path = {
+        let edits = REMOTE_EDITS.lock().unwrap();
+        edits
+            .as_ref()
+            .and_then(|e| e.get(&edit_id))
+            .map(|e| e.local_path.clone())
+            .ok_or("Remote edit not found")?
+    };
+
+    let editor = editor
+        .filter(|e| !e.trim().is_empty())
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok());
+
+    let mut cmd = match editor {
+        Some(editor) => {
+            let mut parts = editor.split_whitespace();
+            let program = parts.next().ok_or("Editor command is empty")?;
+            let mut cmd = std::process::Command::new(program);
+            cmd.args(parts);
+            cmd
+        }
+        None if cfg!(target_os = "macos") => std::process::Command::new("open"),
+        None if cfg!(target_os = "windows") => {
+            let mut cmd = std::process::Command::new("cmd");
+            cmd.args(["/C", "start", ""]);
+            cmd
+        }
+        None => std::process::Command::new("xdg-open"),
+    };
+
+    cmd.arg(&local_path)
+        .spawn()
+        .map_err(|e| format!("Failed to launch editor: {}", e))?;
+    Ok(())
+}
+
+#[command]
+pub async fn save_remote_file(
+    edit_id: String,
+    content: Option<String>,
+    force: Option<bool>,
+) -> Result<RemoteSaveResult, String> {
+    let (target, remote_path, local_path, original_hash) = {
+        let edits = REMOTE_EDITS.lock().unwrap();
+        let edit = edits
+            .as_ref()
+            .and_then(|e| e.get(&edit_id))
+            .ok_or("Remote edit not found")?;
+        (
+            edit.target.clone(),
+            edit.remote_path.clone(),
+            edit.local_path.clone(),
+            edit.original_hash.clone(),
+        )
+    };
+
+    // Contents from the in-app editor, otherwise whatever the local editor saved
+    let data = match content {
+        Some(content) => {
+            std::fs::write(&local_path, &content)
+                .map_err(|e| format!("Failed to write local copy: {}", e))?;
+            content.into_bytes()
+        }
+        None => std::fs::read(&local_path)
+            .map_err(|e| format!("Failed to read local copy: {}", e))?,
+    };
+    if data.len() > MAX_EDIT_BYTES {
+        return Err(format!("Edited file is too large to upload ({} bytes)", data.len()));
+    }
+
+    if !force.unwrap_or(false) {
+        let current = remote_hash(&target, &remote_path).await?;
+        if current != original_hash {
+            return Err(format!(
+                "{} has changed in the container since it was opened",
+                remote_path
+            ));
+        }
+    }
+
+    let tag = edit_id.split('-').next().unwrap_or("edit");
+    let backup_path = format!("{}.bak-{}", remote_path, chrono::Utc::now().format("%Y%m%d%H%M%S"));
+    let backup = format!("cp -p -- {} {}", shell_quote(&remote_path), shell_quote(&backup_path));
+    let out = run_exec_command(&target, &backup, Duration::from_secs(TRANSFER_TIMEOUT_SECS)).await?;
+    if out.exit_code != Some(0) {
+        return Err(format!("Failed to back up {}: {}", remote_path, out.output.trim()));
+    }
+
+    upload_remote_file(&target, &remote_path, &data, tag).await?;
+
+    // Later saves compare against what we just wrote
+    let hash = sha256_hex(&data);
+    if let Some(edit) = REMOTE_EDITS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|e| e.get_mut(&edit_id))
+    {
+        edit.original_hash = hash.clone();
+    }
+
+    Ok(RemoteSaveResult { hash, backup_path })
+}
+
+#[command]
+pub fn discard_remote_edit(edit_id: String) -> Result<(), String> {
+    let edit = REMOTE_EDITS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|e| e.remove(&edit_id));
+
+    if let Some(dir) = edit.as_ref().and_then(|e| e.local_path.parent()) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    Ok(())
+}
@@ -1,8 +1,33 @@
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-
+mod audit;
 mod aws;
+mod clusters;
+mod confirm;
+mod debug_tasks;
+mod diagnostics;
+mod ecs_api;
+mod environment;
+mod exec;
+mod files;
+mod logs;
+mod operations;
+mod paste;
+mod processes;
+mod profiles;
+mod scheduler;
+mod scripts;
+mod services;
+mod shells;
+mod snippets;
+mod ssm;
+mod storage;
+mod task_definitions;
 mod terminal;
+mod tray;
+mod triggers;
+mod tunnels;
+mod watchers;
 
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -10,18 +35,102 @@ pub fn run() {
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .setup(|app| {
+            scheduler::start(app.handle().clone());
+            tunnels::start_auto_stacks(app.handle().clone());
+            watchers::start(app.handle().clone());
+            tray::setup(app.handle())?;
+            Ok(())
+        })
+        .on_window_event(tray::on_window_event)
         .invoke_handler(tauri::generate_handler![
             aws::sso_login,
             aws::cancel_sso_login,
             aws::list_aws_profiles,
             aws::ecs_list_clusters,
+            clusters::ecs_describe_clusters,
             aws::ecs_list_services,
+            services::ecs_describe_services,
+            task_definitions::get_task_definition,
+            task_definitions::diff_task_definitions,
+            task_definitions::diff_service_deployments,
+            environment::get_container_environment,
+            environment::compare_live_environment,
+            operations::request_force_redeploy,
+            operations::force_redeploy,
+            operations::request_scale_service,
+            operations::scale_service,
+            operations::request_stop_tasks,
+            operations::stop_tasks,
+            debug_tasks::launch_debug_task,
             aws::ecs_list_tasks,
             aws::ecs_describe_tasks,
             aws::check_required_tools,
+            exec::exec_fan_out,
+            files::list_remote_dir,
+            files::open_remote_file,
+            files::launch_local_editor,
+            files::save_remote_file,
+            files::discard_remote_edit,
+            logs::get_container_log_source,
+            logs::start_log_tail,
+            logs::stop_log_tail,
+            logs::search_service_logs,
+            logs::export_service_logs,
+            diagnostics::get_diagnostics_recipe,
+            diagnostics::save_diagnostics_recipe,
+            diagnostics::collect_diagnostics,
+            processes::list_remote_processes,
+            processes::request_signal_confirmation,
+            processes::send_remote_signal,
+            shells::detect_container_shell,
             terminal::start_exec_session,
             terminal::write_exec_stdin,
             terminal::close_exec_session,
+            terminal::resize_exec_session,
+            terminal::get_session_info,
+            terminal::set_session_production,
+            terminal::confirm_paste,
+            terminal::discard_paste,
+            terminal::set_session_group,
+            terminal::remove_from_session_group,
+            terminal::delete_session_group,
+            triggers::set_session_triggers,
+            triggers::clear_session_triggers,
+            scripts::run_session_script,
+            scheduler::list_scheduled_jobs,
+            scheduler::save_scheduled_job,
+            scheduler::delete_scheduled_job,
+            scheduler::get_job_history,
+            scheduler::run_scheduled_job_now,
+            audit::get_audit_log,
+            profiles::list_connection_profiles,
+            profiles::save_connection_profile,
+            profiles::delete_connection_profile,
+            profiles::connect_with_profile,
+            snippets::list_snippets,
+            snippets::save_snippet,
+            snippets::delete_snippet,
+            snippets::get_snippet_params,
+            snippets::run_snippet,
+            snippets::export_snippets,
+            snippets::import_snippets,
+            tunnels::list_tunnels,
+            tunnels::save_tunnel,
+            tunnels::delete_tunnel,
+            tunnels::list_tunnel_stacks,
+            tunnels::save_tunnel_stack,
+            tunnels::delete_tunnel_stack,
+            tunnels::start_tunnel,
+            tunnels::stop_tunnel,
+            tunnels::start_tunnel_stack,
+            tunnels::stop_tunnel_stack,
+            tunnels::get_tunnel_status,
+            tunnels::get_tunnel_stack_status,
+            watchers::list_watchers,
+            watchers::save_watcher,
+            watchers::delete_watcher,
+            watchers::get_watcher_history,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,864 @@
+use aws_sdk_cloudwatchlogs::error::DisplayErrorContext;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, Emitter, Manager, Window};
+use tokio::sync::oneshot;
+
+use crate::aws::{run_json_async, task_id_from_arn};
+use crate::ecs_api;
+use crate::exec::ExecTarget;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+// Events per FilterLogEvents page
+const PAGE_LIMIT: i32 = 500;
+// Pages fetched back to back while catching up, before waiting for the next tick
+const MAX_PAGES_PER_POLL: usize = 5;
+const MAX_EVENTS_PER_EMIT: usize = 200;
+// Stop polling while this many events wait to be sent to the UI. The read
+// position is kept, so a slow consumer delays events rather than losing them.
+const MAX_BUFFERED_EVENTS: usize = 2000;
+const DEFAULT_SINCE_SECS: u64 = 300;
+
+static LOG_TAILS: Mutex<Option<HashMap<String, oneshot::Sender<()>>>> = Mutex::new(None);
+
+// Where a container's awslogs driver sends its output
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSource {
+    log_group: String,
+    // Known only with awslogs-stream-prefix, otherwise the whole group is read
+    log_stream: Option<String>,
+    region: String,
+    stream_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogTail {
+    tail_id: String,
+    source: LogSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEvent {
+    event_id: String,
+    timestamp: i64,
+    log_stream: Option<String>,
+    message: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogEventBatch {
+    events: Vec<LogEvent>,
+    // Events still queued behind this batch
+    backlog: usize,
+}
+
+async fn describe_task_definition(
+    profile: &str,
+    region: &str,
+    task_definition: &str,
+) -> Result<serde_json::Value, String> {
+    run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-task-definition",
+            "--task-definition",
+            task_definition,
+            "--region",
+            region,
+            "--profile",
+            profile,
+            "--output",
+            "json",
+        ],
+    )
+    .await
+}
+
+// Read the awslogs options of a container definition. The stream is
+// {prefix}/{container}/{task id} when a task id and stream prefix are known.
+fn awslogs_source(
+    definition: &serde_json::Value,
+    region: &str,
+    task_id: Option<&str>,
+) -> Result<LogSource, String> {
+    let container = definition["name"].as_str().unwrap_or_default();
+    let log_configuration = &definition["logConfiguration"];
+    let driver = log_configuration["logDriver"].as_str().unwrap_or("none");
+    if driver != "awslogs" {
+        return Err(format!(
+            "Container {} uses the {} log driver, only awslogs can be read",
+            container, driver
+        ));
+    }
+
+    let options = &log_configuration["options"];
+    let option = |name: &str| options[name].as_str().map(|s| s.to_string());
+    let log_group = option("awslogs-group").ok_or("awslogs-group is not set")?;
+    let stream_prefix = option("awslogs-stream-prefix");
+    let log_stream = match (&stream_prefix, task_id) {
+        (Some(prefix), Some(task_id)) => Some(format!("{}/{}/{}", prefix, container, task_id)),
+        _ => None,
+    };
+
+    Ok(LogSource {
+        log_group,
+        log_stream,
+        region: option("awslogs-region").unwrap_or_else(|| region.to_string()),
+        stream_prefix,
+    })
+}
+
+pub(crate) async fn container_log_source(
+    profile: &str,
+    region: &str,
+    task_definition: &str,
+    container: &str,
+    task_id: Option<&str>,
+) -> Result<LogSource, String> {
+    let td = describe_task_definition(profile, region, task_definition).await?;
+    let definitions = td["taskDefinition"]["containerDefinitions"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let definition = definitions
+        .iter()
+        .find(|c| c["name"].as_str() == Some(container))
+        .ok_or_else(|| format!("Container {} is not in {}", container, task_definition))?;
+    awslogs_source(definition, region, task_id)
+}
+
+async fn task_log_source(target: &ExecTarget) -> Result<LogSource, String> {
+    let v = run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-tasks",
+            "--cluster",
+            &target.cluster,
+            "--tasks",
+            &target.task,
+            "--region",
+            &target.region,
+            "--profile",
+            &target.profile,
+            "--output",
+            "json",
+        ],
+    )
+    .await?;
+    let task_definition = v["tasks"][0]["taskDefinitionArn"]
+        .as_str()
+        .ok_or("Task not found")?;
+
+    container_log_source(
+        &target.profile,
+        &target.region,
+        task_definition,
+        &target.container,
+        Some(&task_id_from_arn(&target.task)),
+    )
+    .await
+}
+
+// Read position of a tail. While a next token is held the start time must not
+// move, afterwards polling resumes from the newest timestamp seen.
+struct TailCursor {
+    start_time: i64,
+    next_token: Option<String>,
+    // Events at start_time already delivered, FilterLogEvents returns them again
+    seen_at_start: HashSet<String>,
+    newest: i64,
+    seen_at_newest: HashSet<String>,
+}
+
+async fn poll(
+    client: &aws_sdk_cloudwatchlogs::Client,
+    source: &LogSource,
+    filter_pattern: Option<&str>,
+    cursor: &mut TailCursor,
+    buffer: &mut VecDeque<LogEvent>,
+) -> Result<(), String> {
+    let out = client
+        .filter_log_events()
+        .log_group_name(&source.log_group)
+        .set_log_stream_names(source.log_stream.clone().map(|s| vec![s]))
+        .start_time(cursor.start_time)
+        .set_filter_pattern(filter_pattern.map(|s| s.to_string()))
+        .set_next_token(cursor.next_token.clone())
+        .limit(PAGE_LIMIT)
+        .send()
+        .await
+        .map_err(|e| format!("FilterLogEvents failed: {}", DisplayErrorContext(e)))?;
+
+    for event in out.events() {
+        let (Some(event_id), Some(timestamp)) = (event.event_id(), event.timestamp()) else {
+            continue;
+        };
+        if timestamp == cursor.start_time && cursor.seen_at_start.contains(event_id) {
+            continue;
+        }
+        if timestamp > cursor.newest {
+            cursor.newest = timestamp;
+            cursor.seen_at_newest.clear();
+        }
+        if timestamp == cursor.newest {
+            cursor.seen_at_newest.insert(event_id.to_string());
+        }
+        buffer.push_back(LogEvent {
+            event_id: event_id.to_string(),
+            timestamp,
+            log_stream: event.log_stream_name().map(|s| s.to_string()),
+            message: event
+                .message()
+                .unwrap_or_default()
+                .trim_end_matches('\n')
+                .to_string(),
+        });
+    }
+
+    match out.next_token() {
+        Some(token) => cursor.next_token = Some(token.to_string()),
+        None => {
+            // Resume from the newest event, skipping those already delivered at it
+            cursor.next_token = None;
+            cursor.start_time = cursor.newest;
+            cursor.seen_at_start = cursor.seen_at_newest.clone();
+        }
+    }
+    Ok(())
+}
+
+async fn run_tail(
+    window: Window,
+    tail_id: String,
+    source: LogSource,
+    profile: String,
+    filter_pattern: Option<String>,
+    since_secs: u64,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let client =
+        aws_sdk_cloudwatchlogs::Client::new(&ecs_api::sdk_config(&profile, &source.region).await);
+    let start_time = chrono::Utc::now().timestamp_millis() - (since_secs as i64) * 1000;
+    let mut cursor = TailCursor {
+        start_time,
+        next_token: None,
+        seen_at_start: HashSet::new(),
+        newest: start_time,
+        seen_at_newest: HashSet::new(),
+    };
+    let mut buffer = VecDeque::new();
+
+    let mut poll_timer = tokio::time::interval(POLL_INTERVAL);
+    let mut emit_timer = tokio::time::interval(EMIT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = &mut stop_rx => break,
+            _ = poll_timer.tick() => {
+                if buffer.len() >= MAX_BUFFERED_EVENTS {
+                    continue;
+                }
+                // Follow a held next token straight away instead of waiting a full interval
+                for _ in 0..MAX_PAGES_PER_POLL {
+                    if let Err(e) = poll(&client, &source, filter_pattern.as_deref(), &mut cursor, &mut buffer).await {
+                        eprintln!("[DEBUG] Log tail {} poll failed: {}", tail_id, e);
+                        let _ = window.emit(&format!("logs:error:{}", tail_id), e);
+                        break;
+                    }
+                    if cursor.next_token.is_none() || buffer.len() >= MAX_BUFFERED_EVENTS {
+                        break;
+                    }
+                }
+            }
+            _ = emit_timer.tick() => {
+                if buffer.is_empty() {
+                    continue;
+                }
+                let count = buffer.len().min(MAX_EVENTS_PER_EMIT);
+                let events: Vec<LogEvent> = buffer.drain(..count).collect();
+                let _ = window.emit(
+                    &format!("logs:events:{}", tail_id),
+                    LogEventBatch {
+                        events,
+                        backlog: buffer.len(),
+                    },
+                );
+            }
+        }
+    }
+
+    if let Some(ref mut tails) = *LOG_TAILS.lock().unwrap() {
+        tails.remove(&tail_id);
+    }
+    let _ = window.emit(&format!("logs:end:{}", tail_id), ());
+}
+
+#[command]
+pub async fn get_container_log_source(target: ExecTarget) -> Result<LogSource, String> {
+    task_log_source(&target).await
+}
+
+// Follow a container's CloudWatch log stream. Events arrive in batches on
+// logs:events:{tailId} until stop_log_tail is called.
+#[command]
+pub async fn start_log_tail(
+    window: Window,
+    target: ExecTarget,
+    filter_pattern: Option<String>,
+    since_secs: Option<u64>,
+) -> Result<LogTail, String> {
+    let source = task_log_source(&target).await?;
+    let filter_pattern = filter_pattern.filter(|p| !p.trim().is_empty());
+
+    let tail_id = uuid::Uuid::new_v4().to_string();
+    let (stop_tx, stop_rx) = oneshot::channel();
+    LOG_TAILS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(tail_id.clone(), stop_tx);
+
+    tauri::async_runtime::spawn(run_tail(
+        window,
+        tail_id.clone(),
+        source.clone(),
+        target.profile,
+        filter_pattern,
+        since_secs.unwrap_or(DEFAULT_SINCE_SECS),
+        stop_rx,
+    ));
+
+    Ok(LogTail { tail_id, source })
+}
+
+#[command]
+pub fn stop_log_tail(tail_id: String) -> Result<(), String> {
+    let stop = LOG_TAILS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|t| t.remove(&tail_id))
+        .ok_or("Log tail not found")?;
+    let _ = stop.send(());
+    Ok(())
+}
+
+// Historical search
+
+// Results per page when the request does not set a limit
+const DEFAULT_SEARCH_LIMIT: usize = 200;
+// FilterLogEvents and Logs Insights both cap a page at 10000 rows
+const MAX_SEARCH_LIMIT: usize = 10000;
+const QUERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const QUERY_TIMEOUT: Duration = Duration::from_secs(120);
+// Exports stop here so a broad search cannot fill the disk
+const MAX_EXPORT_RECORDS: usize = 100_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSearchRequest {
+    profile: String,
+    region: String,
+    cluster: String,
+    service: String,
+    // Defaults to the first container with awslogs in the task definition
+    container: Option<String>,
+    // Milliseconds since the epoch
+    start_time: i64,
+    end_time: i64,
+    filter_pattern: Option<String>,
+    // A Logs Insights query, used instead of the filter pattern when set
+    query: Option<String>,
+    limit: Option<usize>,
+}
+
+// Where the next page starts. FilterLogEvents hands out a token, Insights
+// results are kept by CloudWatch and read again from an offset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSearchCursor {
+    next_token: Option<String>,
+    query_id: Option<String>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRecord {
+    timestamp: i64,
+    task_id: Option<String>,
+    log_stream: Option<String>,
+    message: String,
+    // Top level fields of a JSON message, plus any extra Insights fields
+    fields: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSearchPage {
+    source: LogSource,
+    container: String,
+    task_definition: String,
+    records: Vec<LogRecord>,
+    // None once the search is exhausted
+    cursor: Option<LogSearchCursor>,
+    // The Logs Insights query stopped at MAX_SEARCH_LIMIT rows, later matches are missing
+    capped: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogExport {
+    path: String,
+    records: usize,
+    // True when MAX_EXPORT_RECORDS was reached before the search ran out, or
+    // the Logs Insights query stopped at its row limit
+    truncated: bool,
+}
+
+struct ServiceLogSource {
+    source: LogSource,
+    container: String,
+    task_definition: String,
+}
+
+// Resolve the log group from the task definition the service currently runs
+async fn service_log_source(request: &LogSearchRequest) -> Result<ServiceLogSource, String> {
+    let v = run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-services",
+            "--cluster",
+            &request.cluster,
+            "--services",
+            &request.service,
+            "--region",
+            &request.region,
+            "--profile",
+            &request.profile,
+            "--output",
+            "json",
+        ],
+    )
+    .await?;
+    let task_definition = v["services"][0]["taskDefinition"]
+        .as_str()
+        .ok_or("Service not found")?
+        .to_string();
+
+    let td = describe_task_definition(&request.profile, &request.region, &task_definition).await?;
+    let definitions = td["taskDefinition"]["containerDefinitions"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let definition = match &request.container {
+        Some(container) => definitions
+            .iter()
+            .find(|c| c["name"].as_str() == Some(container.as_str()))
+            .ok_or_else(|| format!("Container {} is not in {}", container, task_definition))?,
+        None => definitions
+            .iter()
+            .find(|c| c["logConfiguration"]["logDriver"].as_str() == Some("awslogs"))
+            .ok_or_else(|| format!("No container in {} uses awslogs", task_definition))?,
+    };
+
+    Ok(ServiceLogSource {
+        source: awslogs_source(definition, &request.region, None)?,
+        container: definition["name"].as_str().unwrap_or_default().to_string(),
+        task_definition,
+    })
+}
+
+// Streams of one container share {prefix}/{container}/, the rest is the task id
+fn stream_prefix(source: &LogSource, container: &str) -> Option<String> {
+    source
+        .stream_prefix
+        .as_ref()
+        .map(|prefix| format!("{}/{}/", prefix, container))
+}
+
+fn log_record(
+    timestamp: i64,
+    log_stream: Option<String>,
+    message: &str,
+    prefix: Option<&str>,
+) -> LogRecord {
+    let message = message.trim_end_matches('\n').to_string();
+    let task_id = match (&log_stream, prefix) {
+        (Some(stream), Some(prefix)) => stream.strip_prefix(prefix).map(|s| s.to_string()),
+        _ => None,
+    };
+    let fields = if message.trim_start().starts_with('{') {
+        serde_json::from_str::<serde_json::Value>(&message)
+            .ok()
+            .and_then(|v| v.as_object().cloned())
+    } else {
+        None
+    };
+    LogRecord {
+        timestamp,
+        task_id,
+        log_stream,
+        message,
+        fields,
+    }
+}
+
+async fn filter_page(
+    client: &aws_sdk_cloudwatchlogs::Client,
+    request: &LogSearchRequest,
+    resolved: &ServiceLogSource,
+    next_token: Option<String>,
+    limit: usize,
+) -> Result<(Vec<LogRecord>, Option<LogSearchCursor>, bool), String> {
+    let prefix = stream_prefix(&resolved.source, &resolved.container);
+    let out = client
+        .filter_log_events()
+        .log_group_name(&resolved.source.log_group)
+        .set_log_stream_name_prefix(prefix.clone())
+        .start_time(request.start_time)
+        .end_time(request.end_time)
+        .set_filter_pattern(
+            request
+                .filter_pattern
+                .clone()
+                .filter(|p| !p.trim().is_empty()),
+        )
+        .set_next_token(next_token)
+        .limit(limit as i32)
+        .send()
+        .await
+        .map_err(|e| format!("FilterLogEvents failed: {}", DisplayErrorContext(e)))?;
+
+    let records = out
+        .events()
+        .iter()
+        .map(|e| {
+            log_record(
+                e.timestamp().unwrap_or_default(),
+                e.log_stream_name().map(|s| s.to_string()),
+                e.message().unwrap_or_default(),
+                prefix.as_deref(),
+            )
+        })
+        .collect();
+    let cursor = out.next_token().map(|token| LogSearchCursor {
+        next_token: Some(token.to_string()),
+        query_id: None,
+        offset: 0,
+    });
+    Ok((records, cursor, false))
+}
+
+// Insights reports @timestamp as "2024-01-31 12:00:00.000" in UTC
+fn parse_insights_timestamp(value: &str) -> Option<i64> {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|t| t.and_utc().timestamp_millis())
+}
+
+async fn start_insights_query(
+    client: &aws_sdk_cloudwatchlogs::Client,
+    request: &LogSearchRequest,
+    resolved: &ServiceLogSource,
+    query: &str,
+) -> Result<String, String> {
+    // Keep the query to this container's streams, the group may be shared
+    let query = match stream_prefix(&resolved.source, &resolved.container) {
+        Some(prefix) => format!(
+            "filter @logStream like /^{}/ | {}",
+            regex::escape(&prefix).replace('/', "\\/"),
+            query
+        ),
+        None => query.to_string(),
+    };
+    let out = client
+        .start_query()
+        .log_group_names(&resolved.source.log_group)
+        .start_time(request.start_time / 1000)
+        .end_time(request.end_time / 1000)
+        .query_string(query)
+        .limit(MAX_SEARCH_LIMIT as i32)
+        .send()
+        .await
+        .map_err(|e| format!("StartQuery failed: {}", DisplayErrorContext(e)))?;
+    out.query_id()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "StartQuery returned no query id".to_string())
+}
+
+async fn insights_page(
+    client: &aws_sdk_cloudwatchlogs::Client,
+    resolved: &ServiceLogSource,
+    query_id: String,
+    offset: usize,
+    limit: usize,
+) -> Result<(Vec<LogRecord>, Option<LogSearchCursor>, bool), String> {
+    use aws_sdk_cloudwatchlogs::types::QueryStatus;
+
+    let started = std::time::Instant::now();
+    let out = loop {
+        let out = client
+            .get_query_results()
+            .query_id(&query_id)
+            .send()
+            .await
+            .map_err(|e| format!("GetQueryResults failed: {}", DisplayErrorContext(e)))?;
+        match out.status() {
+            Some(QueryStatus::Complete) => break out,
+            Some(QueryStatus::Running) | Some(QueryStatus::Scheduled) | None => {}
+            Some(status) => return Err(format!("Logs Insights query {}", status.as_str())),
+        }
+        if started.elapsed() > QUERY_TIMEOUT {
+            let _ = client.stop_query().query_id(&query_id).send().await;
+            return Err("Logs Insights query timed out".to_string());
+        }
+        tokio::time::sleep(QUERY_POLL_INTERVAL).await;
+    };
+
+    let prefix = stream_prefix(&resolved.source, &resolved.container);
+    let rows = out.results();
+    let records = rows
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|row| {
+            let mut timestamp = 0;
+            let mut log_stream = None;
+            let mut message = String::new();
+            let mut extra = serde_json::Map::new();
+            for field in row {
+                let (Some(name), Some(value)) = (field.field(), field.value()) else {
+                    continue;
+                };
+                match name {
+                    "@timestamp" => timestamp = parse_insights_timestamp(value).unwrap_or_default(),
+                    "@logStream" => log_stream = Some(value.to_string()),
+                    "@message" => message = value.to_string(),
+                    "@ptr" => {}
+                    _ => {
+                        extra.insert(
+                            name.to_string(),
+                            serde_json::Value::String(value.to_string()),
+                        );
+                    }
+                }
+            }
+            let mut record = log_record(timestamp, log_stream, &message, prefix.as_deref());
+            if !extra.is_empty() {
+                record
+                    .fields
+                    .get_or_insert_with(serde_json::Map::new)
+                    .extend(extra);
+            }
+            record
+        })
+        .collect();
+
+    let next = offset + limit;
+    let cursor = (next < rows.len()).then_some(LogSearchCursor {
+        next_token: None,
+        query_id: Some(query_id),
+        offset: next,
+    });
+    // Insights hands back no more than the limit and says nothing of the rest
+    let capped = rows.len() >= MAX_SEARCH_LIMIT;
+    Ok((records, cursor, capped))
+}
+
+async fn search_page(
+    client: &aws_sdk_cloudwatchlogs::Client,
+    request: &LogSearchRequest,
+    resolved: &ServiceLogSource,
+    cursor: Option<LogSearchCursor>,
+) -> Result<(Vec<LogRecord>, Option<LogSearchCursor>, bool), String> {
+    let limit = request
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let query = request.query.as_deref().filter(|q| !q.trim().is_empty());
+
+    match (query, cursor) {
+        (
+            Some(_),
+            Some(LogSearchCursor {
+                query_id: Some(query_id),
+                offset,
+                ..
+            }),
+        ) => insights_page(client, resolved, query_id, offset, limit).await,
+        (Some(query), _) => {
+            let query_id = start_insights_query(client, request, resolved, query).await?;
+            insights_page(client, resolved, query_id, 0, limit).await
+        }
+        (None, cursor) => {
+            let next_token = cursor.and_then(|c| c.next_token);
+            filter_page(client, request, resolved, next_token, limit).await
+        }
+    }
+}
+
+fn validate_search(request: &LogSearchRequest) -> Result<(), String> {
+    if request.end_time <= request.start_time {
+        return Err("The end of the time range must be after its start".to_string());
+    }
+    Ok(())
+}
+
+// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_line(record: &LogRecord, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Jsonl => serde_json::to_string(record)
+            .map(|line| line + "\n")
+            .map_err(|e| format!("Failed to encode log record: {}", e)),
+        ExportFormat::Csv => {
+            let timestamp = chrono::DateTime::from_timestamp_millis(record.timestamp)
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                .unwrap_or_default();
+            let fields = record
+                .fields
+                .as_ref()
+                .map(|f| serde_json::Value::Object(f.clone()).to_string())
+                .unwrap_or_default();
+            Ok(format!(
+                "{},{},{},{},{}\n",
+                csv_field(&timestamp),
+                csv_field(record.task_id.as_deref().unwrap_or_default()),
+                csv_field(record.log_stream.as_deref().unwrap_or_default()),
+                csv_field(&record.message),
+                csv_field(&fields)
+            ))
+        }
+    }
+}
+
+// Search a service's logs over a time range, one page per call. Pass the
+// returned cursor back with the same request to read the next page.
+#[command]
+pub async fn search_service_logs(
+    request: LogSearchRequest,
+    cursor: Option<LogSearchCursor>,
+) -> Result<LogSearchPage, String> {
+    validate_search(&request)?;
+    let resolved = service_log_source(&request).await?;
+    let client = aws_sdk_cloudwatchlogs::Client::new(
+        &ecs_api::sdk_config(&request.profile, &resolved.source.region).await,
+    );
+
+    let (records, cursor, capped) = search_page(&client, &request, &resolved, cursor).await?;
+    Ok(LogSearchPage {
+        source: resolved.source,
+        container: resolved.container,
+        task_definition: resolved.task_definition,
+        records,
+        cursor,
+        capped,
+    })
+}
+
+// Run a search to the end and write every record to a file. Without a path
+// the file goes to the app data directory. Progress is emitted on
+// logs:export:progress.
+#[command]
+pub async fn export_service_logs(
+    window: Window,
+    request: LogSearchRequest,
+    format: ExportFormat,
+    path: Option<String>,
+) -> Result<LogExport, String> {
+    validate_search(&request)?;
+    let resolved = service_log_source(&request).await?;
+    let client = aws_sdk_cloudwatchlogs::Client::new(
+        &ecs_api::sdk_config(&request.profile, &resolved.source.region).await,
+    );
+
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let dir = window
+                .app_handle()
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+                .join("log-exports");
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            let extension = match format {
+                ExportFormat::Jsonl => "jsonl",
+                ExportFormat::Csv => "csv",
+            };
+            let service = request
+                .service
+                .rsplit('/')
+                .next()
+                .unwrap_or(&request.service);
+            dir.join(format!(
+                "{}-{}.{}",
+                service,
+                chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+                extension
+            ))
+        }
+    };
+
+    let mut file = std::fs::File::create(&path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut write = |data: &str| {
+        file.write_all(data.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    };
+    if let ExportFormat::Csv = format {
+        write("timestamp,taskId,logStream,message,fields\n")?;
+    }
+
+    let mut exported = 0;
+    let mut cursor = None;
+    let truncated = loop {
+        let (records, next, capped) = search_page(&client, &request, &resolved, cursor).await?;
+        for record in &records {
+            write(&export_line(record, format)?)?;
+        }
+        exported += records.len();
+        let _ = window.emit("logs:export:progress", exported);
+
+        if exported >= MAX_EXPORT_RECORDS {
+            break next.is_some() || capped;
+        }
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break capped,
+        }
+    };
+
+    Ok(LogExport {
+        path: path.to_string_lossy().to_string(),
+        records: exported,
+        truncated,
+    })
+}
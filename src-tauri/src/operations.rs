@@ -0,0 +1,682 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, Window};
+
+use crate::audit;
+use crate::aws::task_id_from_arn;
+use crate::confirm::{self, ConfirmationRequest};
+use crate::ecs_api;
+use crate::profiles;
+use crate::services::describe_service;
+
+const REDEPLOY_ACTION: &str = "force_redeploy";
+const SCALE_ACTION: &str = "scale_service";
+const STOP_TASKS_ACTION: &str = "stop_tasks";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+const REDEPLOY_TIMEOUT: Duration = Duration::from_secs(1800);
+const SCALE_TIMEOUT: Duration = Duration::from_secs(900);
+const STOP_TIMEOUT: Duration = Duration::from_secs(600);
+// StopTask reasons are shown in the console and capped by the API
+const MAX_REASON_LEN: usize = 255;
+// DescribeTasks accepts at most this many tasks, progress is followed in one call
+const MAX_STOP_TASKS: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceTarget {
+    profile: String,
+    region: String,
+    cluster: String,
+    service: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScaleRequest {
+    target: ServiceTarget,
+    desired_count: i32,
+    // Typed back by the user when the service is protected
+    confirm_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RedeployRequest {
+    target: ServiceTarget,
+    confirm_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StopTasksRequest {
+    profile: String,
+    region: String,
+    cluster: String,
+    service: Option<String>,
+    tasks: Vec<String>,
+    reason: String,
+    confirm_text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationConfirmation {
+    #[serde(flatten)]
+    confirmation: ConfirmationRequest,
+    // A production profile covers the target, confirm_text must be typed to proceed
+    production: bool,
+    confirm_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationStatus {
+    InProgress,
+    Succeeded,
+    Failed,
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationProgress {
+    operation_id: String,
+    action: String,
+    status: OperationStatus,
+    message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationStarted {
+    operation_id: String,
+}
+
+fn short_name(arn: &str) -> &str {
+    arn.rsplit('/').next().unwrap_or(arn)
+}
+
+fn confirmation(
+    action: &'static str,
+    summary: String,
+    production_text: Option<String>,
+    payload: serde_json::Value,
+) -> OperationConfirmation {
+    let production = production_text.is_some();
+    let summary = if production {
+        format!("PRODUCTION: {}", summary)
+    } else {
+        summary
+    };
+    OperationConfirmation {
+        confirmation: confirm::issue(action, summary, payload),
+        production,
+        confirm_text: production_text,
+    }
+}
+
+// Production targets need their name typed back, a stray click is not enough
+fn check_typed(expected: &Option<String>, typed: Option<&str>) -> Result<(), String> {
+    match expected {
+        Some(expected) if typed.map(str::trim) != Some(expected.as_str()) => Err(format!(
+            "Type {} to confirm this production operation, then request a new confirmation",
+            expected
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn emit_progress(
+    app: &AppHandle,
+    operation_id: &str,
+    action: &str,
+    status: OperationStatus,
+    message: String,
+) {
+    let _ = app.emit(
+        "operation:progress",
+        OperationProgress {
+            operation_id: operation_id.to_string(),
+            action: action.to_string(),
+            status,
+            message,
+        },
+    );
+}
+
+// Poll until check reports a final status or the timeout passes, emitting
+// each intermediate message
+fn follow<F, Fut>(
+    app: AppHandle,
+    operation_id: String,
+    action: &'static str,
+    timeout: Duration,
+    check: F,
+) where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(OperationStatus, String), String>> + Send,
+{
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        loop {
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+            let (status, message) = match check().await {
+                Ok(progress) => progress,
+                // A failed poll is not a failed operation, keep following it
+                Err(e) => (OperationStatus::InProgress, e),
+            };
+            let status = match status {
+                OperationStatus::InProgress if started.elapsed() > timeout => {
+                    OperationStatus::TimedOut
+                }
+                status => status,
+            };
+            emit_progress(&app, &operation_id, action, status, message);
+            if !matches!(status, OperationStatus::InProgress) {
+                break;
+            }
+        }
+    });
+}
+
+async fn redeploy_progress(
+    target: &ServiceTarget,
+    deployment_id: &str,
+) -> Result<(OperationStatus, String), String> {
+    let service = describe_service(
+        &target.profile,
+        &target.region,
+        &target.cluster,
+        &target.service,
+    )
+    .await?;
+    let Some(deployment) = service.deployments.iter().find(|d| d.id == deployment_id) else {
+        return Ok((
+            OperationStatus::Failed,
+            "The deployment was replaced by a newer one".to_string(),
+        ));
+    };
+
+    let counts = format!(
+        "{} of {} tasks running, {} pending",
+        deployment.running_count, deployment.desired_count, deployment.pending_count
+    );
+    match deployment.rollout_state.as_deref() {
+        Some("COMPLETED") => Ok((
+            OperationStatus::Succeeded,
+            format!("Rollout completed, {}", counts),
+        )),
+        Some("FAILED") => Ok((
+            OperationStatus::Failed,
+            format!(
+                "Rollout failed: {}",
+                deployment
+                    .rollout_state_reason
+                    .as_deref()
+                    .unwrap_or("no reason given")
+            ),
+        )),
+        // Without a circuit breaker the rollout is done once it is the only deployment
+        None if service.deployments.len() == 1
+            && deployment.running_count == deployment.desired_count =>
+        {
+            Ok((
+                OperationStatus::Succeeded,
+                format!("Rollout completed, {}", counts),
+            ))
+        }
+        _ => Ok((
+            OperationStatus::InProgress,
+            format!("Rolling out, {}", counts),
+        )),
+    }
+}
+
+async fn scale_progress(
+    target: &ServiceTarget,
+    desired_count: i32,
+) -> Result<(OperationStatus, String), String> {
+    let service = describe_service(
+        &target.profile,
+        &target.region,
+        &target.cluster,
+        &target.service,
+    )
+    .await?;
+
+    let message = format!(
+        "{} of {} tasks running, {} pending",
+        service.running_count, desired_count, service.pending_count
+    );
+    if service.desired_count != desired_count {
+        return Ok((
+            OperationStatus::Failed,
+            format!(
+                "Desired count was changed to {} elsewhere",
+                service.desired_count
+            ),
+        ));
+    }
+    let settled = service.running_count == desired_count && service.pending_count == 0;
+    let status = if settled {
+        OperationStatus::Succeeded
+    } else {
+        OperationStatus::InProgress
+    };
+    Ok((status, message))
+}
+
+async fn stop_progress(request: &StopTasksRequest) -> Result<(OperationStatus, String), String> {
+    let out = ecs_api::client(&request.profile, &request.region)
+        .await
+        .describe_tasks()
+        .cluster(&request.cluster)
+        .set_tasks(Some(request.tasks.clone()))
+        .send()
+        .await
+        .map_err(|e| format!("DescribeTasks failed: {}", DisplayErrorContext(e)))?;
+
+    let stopped = out
+        .tasks()
+        .iter()
+        .filter(|t| t.last_status() == Some("STOPPED"))
+        .count();
+    // Tasks ECS has already forgotten are long stopped
+    let stopped = stopped + request.tasks.len().saturating_sub(out.tasks().len());
+    let message = format!("{} of {} tasks stopped", stopped, request.tasks.len());
+    let status = if stopped >= request.tasks.len() {
+        OperationStatus::Succeeded
+    } else {
+        OperationStatus::InProgress
+    };
+    Ok((status, message))
+}
+
+#[command]
+pub fn request_force_redeploy(
+    window: Window,
+    target: ServiceTarget,
+) -> Result<OperationConfirmation, String> {
+    let app = window.app_handle();
+    let production = profiles::is_production(
+        app,
+        &target.profile,
+        &target.region,
+        &target.cluster,
+        Some(&target.service),
+    );
+    let name = short_name(&target.service).to_string();
+    let summary = format!(
+        "Force a new deployment of {} in {}, replacing every running task",
+        name,
+        short_name(&target.cluster)
+    );
+    let production_text = production.then(|| name.clone());
+    let payload = serde_json::to_value(RedeployRequest {
+        target,
+        confirm_text: production_text.clone(),
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(confirmation(
+        REDEPLOY_ACTION,
+        summary,
+        production_text,
+        payload,
+    ))
+}
+
+#[command]
+pub async fn force_redeploy(
+    window: Window,
+    token: String,
+    confirm_text: Option<String>,
+) -> Result<OperationStarted, String> {
+    let request: RedeployRequest =
+        serde_json::from_value(confirm::redeem(REDEPLOY_ACTION, &token)?)
+            .map_err(|e| e.to_string())?;
+    check_typed(&request.confirm_text, confirm_text.as_deref())?;
+    let target = request.target;
+
+    let out = ecs_api::client(&target.profile, &target.region)
+        .await
+        .update_service()
+        .cluster(&target.cluster)
+        .service(&target.service)
+        .force_new_deployment(true)
+        .send()
+        .await
+        .map_err(|e| format!("UpdateService failed: {}", DisplayErrorContext(e)))?;
+    let deployment_id = out
+        .service()
+        .and_then(|s| {
+            s.deployments()
+                .iter()
+                .find(|d| d.status() == Some("PRIMARY"))
+        })
+        .and_then(|d| d.id())
+        .ok_or("UpdateService returned no new deployment")?
+        .to_string();
+
+    let app = window.app_handle().clone();
+    audit::record(
+        &app,
+        REDEPLOY_ACTION,
+        json!({
+            "cluster": target.cluster,
+            "service": target.service,
+            "deploymentId": deployment_id,
+        }),
+    );
+
+    let operation_id = uuid::Uuid::new_v4().to_string();
+    emit_progress(
+        &app,
+        &operation_id,
+        REDEPLOY_ACTION,
+        OperationStatus::InProgress,
+        format!("Deployment {} started", deployment_id),
+    );
+    follow(
+        app,
+        operation_id.clone(),
+        REDEPLOY_ACTION,
+        REDEPLOY_TIMEOUT,
+        move || {
+            let target = target.clone();
+            let deployment_id = deployment_id.clone();
+            async move { redeploy_progress(&target, &deployment_id).await }
+        },
+    );
+    Ok(OperationStarted { operation_id })
+}
+
+#[command]
+pub fn request_scale_service(
+    window: Window,
+    target: ServiceTarget,
+    desired_count: i32,
+) -> Result<OperationConfirmation, String> {
+    if desired_count < 0 {
+        return Err("Desired count cannot be negative".to_string());
+    }
+    let app = window.app_handle();
+    let production = profiles::is_production(
+        app,
+        &target.profile,
+        &target.region,
+        &target.cluster,
+        Some(&target.service),
+    );
+    let name = short_name(&target.service).to_string();
+    let summary = if desired_count == 0 {
+        format!("Scale {} to 0, stopping every task of the service", name)
+    } else {
+        format!("Set the desired count of {} to {}", name, desired_count)
+    };
+    let production_text = production.then(|| name.clone());
+    let payload = serde_json::to_value(ScaleRequest {
+        target,
+        desired_count,
+        confirm_text: production_text.clone(),
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(confirmation(
+        SCALE_ACTION,
+        summary,
+        production_text,
+        payload,
+    ))
+}
+
+#[command]
+pub async fn scale_service(
+    window: Window,
+    token: String,
+    confirm_text: Option<String>,
+) -> Result<OperationStarted, String> {
+    let request: ScaleRequest = serde_json::from_value(confirm::redeem(SCALE_ACTION, &token)?)
+        .map_err(|e| e.to_string())?;
+    check_typed(&request.confirm_text, confirm_text.as_deref())?;
+    let target = request.target;
+    let desired_count = request.desired_count;
+
+    ecs_api::client(&target.profile, &target.region)
+        .await
+        .update_service()
+        .cluster(&target.cluster)
+        .service(&target.service)
+        .desired_count(desired_count)
+        .send()
+        .await
+        .map_err(|e| format!("UpdateService failed: {}", DisplayErrorContext(e)))?;
+
+    let app = window.app_handle().clone();
+    audit::record(
+        &app,
+        SCALE_ACTION,
+        json!({
+            "cluster": target.cluster,
+            "service": target.service,
+            "desiredCount": desired_count,
+        }),
+    );
+
+    let operation_id = uuid::Uuid::new_v4().to_string();
+    emit_progress(
+        &app,
+        &operation_id,
+        SCALE_ACTION,
+        OperationStatus::InProgress,
+        format!("Desired count set to {}", desired_count),
+    );
+    follow(
+        app,
+        operation_id.clone(),
+        SCALE_ACTION,
+        SCALE_TIMEOUT,
+        move || {
+            let target = target.clone();
+            async move { scale_progress(&target, desired_count).await }
+        },
+    );
+    Ok(OperationStarted { operation_id })
+}
+
+// The service each task belongs to, from its group ("service:<name>"), None
+// for standalone tasks
+async fn task_services(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    tasks: &[String],
+) -> Result<Vec<Option<String>>, String> {
+    let out = ecs_api::client(profile, region)
+        .await
+        .describe_tasks()
+        .cluster(cluster)
+        .set_tasks(Some(tasks.to_vec()))
+        .send()
+        .await
+        .map_err(|e| format!("DescribeTasks failed: {}", DisplayErrorContext(e)))?;
+
+    tasks
+        .iter()
+        .map(|arn| {
+            let id = task_id_from_arn(arn);
+            let task = out
+                .tasks()
+                .iter()
+                .find(|t| t.task_arn().is_some_and(|a| task_id_from_arn(a) == id))
+                .ok_or_else(|| format!("Task {} not found in {}", id, short_name(cluster)))?;
+            Ok(task
+                .group()
+                .and_then(|g| g.strip_prefix("service:"))
+                .map(|s| s.to_string()))
+        })
+        .collect()
+}
+
+#[command]
+pub async fn request_stop_tasks(
+    window: Window,
+    profile: String,
+    region: String,
+    cluster: String,
+    service: Option<String>,
+    tasks: Vec<String>,
+    reason: String,
+) -> Result<OperationConfirmation, String> {
+    if tasks.is_empty() {
+        return Err("No tasks to stop".to_string());
+    }
+    if tasks.len() > MAX_STOP_TASKS {
+        return Err(format!(
+            "At most {} tasks can be stopped at once",
+            MAX_STOP_TASKS
+        ));
+    }
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err("A reason is required to stop tasks".to_string());
+    }
+    if reason.chars().count() > MAX_REASON_LEN {
+        return Err(format!(
+            "The reason must be at most {} characters",
+            MAX_REASON_LEN
+        ));
+    }
+
+    // Protection follows the services the tasks really belong to, not the one named
+    let owners = task_services(&profile, &region, &cluster, &tasks).await?;
+    if let Some(ref service) = service {
+        if let Some((task, _)) = tasks
+            .iter()
+            .zip(&owners)
+            .find(|(_, owner)| owner.as_deref() != Some(short_name(service)))
+        {
+            return Err(format!(
+                "Task {} does not belong to service {}",
+                task_id_from_arn(task),
+                short_name(service)
+            ));
+        }
+    }
+    let app = window.app_handle();
+    let production = owners
+        .iter()
+        .any(|owner| profiles::is_production(app, &profile, &region, &cluster, owner.as_deref()));
+    // Tasks of a single service confirm with its name, anything else with the cluster's
+    let single_service = match owners.first() {
+        Some(Some(first)) if owners.iter().all(|o| o.as_deref() == Some(first.as_str())) => {
+            Some(first.clone())
+        }
+        _ => None,
+    };
+    let ids: Vec<String> = tasks.iter().map(|t| task_id_from_arn(t)).collect();
+    let summary = format!(
+        "Stop {} task(s) in {}: {} (reason: {})",
+        tasks.len(),
+        short_name(&cluster),
+        ids.join(", "),
+        reason
+    );
+    let production_text =
+        production.then(|| short_name(single_service.as_deref().unwrap_or(&cluster)).to_string());
+    let payload = serde_json::to_value(StopTasksRequest {
+        profile,
+        region,
+        cluster,
+        service,
+        tasks,
+        reason,
+        confirm_text: production_text.clone(),
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(confirmation(
+        STOP_TASKS_ACTION,
+        summary,
+        production_text,
+        payload,
+    ))
+}
+
+#[command]
+pub async fn stop_tasks(
+    window: Window,
+    token: String,
+    confirm_text: Option<String>,
+) -> Result<OperationStarted, String> {
+    let request: StopTasksRequest =
+        serde_json::from_value(confirm::redeem(STOP_TASKS_ACTION, &token)?)
+            .map_err(|e| e.to_string())?;
+    check_typed(&request.confirm_text, confirm_text.as_deref())?;
+
+    let client = ecs_api::client(&request.profile, &request.region).await;
+    let mut stopping = Vec::new();
+    let mut errors = Vec::new();
+    for task in &request.tasks {
+        match client
+            .stop_task()
+            .cluster(&request.cluster)
+            .task(task)
+            .reason(&request.reason)
+            .send()
+            .await
+        {
+            Ok(_) => stopping.push(task.clone()),
+            Err(e) => errors.push(format!(
+                "{}: {}",
+                task_id_from_arn(task),
+                DisplayErrorContext(e)
+            )),
+        }
+    }
+
+    let app = window.app_handle().clone();
+    audit::record(
+        &app,
+        STOP_TASKS_ACTION,
+        json!({
+            "cluster": request.cluster,
+            "tasks": request.tasks,
+            "reason": request.reason,
+            "errors": errors,
+        }),
+    );
+    if stopping.is_empty() {
+        return Err(format!("StopTask failed: {}", errors.join("; ")));
+    }
+    // Only follow the tasks ECS accepted, the others would never settle
+    let request = StopTasksRequest {
+        tasks: stopping,
+        ..request
+    };
+
+    let operation_id = uuid::Uuid::new_v4().to_string();
+    let message = if errors.is_empty() {
+        format!("Stopping {} task(s)", request.tasks.len())
+    } else {
+        format!("Some tasks could not be stopped: {}", errors.join("; "))
+    };
+    emit_progress(
+        &app,
+        &operation_id,
+        STOP_TASKS_ACTION,
+        OperationStatus::InProgress,
+        message,
+    );
+    follow(
+        app,
+        operation_id.clone(),
+        STOP_TASKS_ACTION,
+        STOP_TIMEOUT,
+        move || {
+            let request = request.clone();
+            async move { stop_progress(&request).await }
+        },
+    );
+    Ok(OperationStarted { operation_id })
+}
@@ -0,0 +1,54 @@
+use serde::Serialize;
+use std::time::Duration;
+
+// Markers a terminal wraps around pasted text when bracketed paste mode is on
+const BRACKETED_PASTE_START: &str = "\x1b[200~";
+const BRACKETED_PASTE_END: &str = "\x1b[201~";
+
+// Writes larger than this are fed to stdin in chunks so the SSM channel keeps up
+pub(crate) const STDIN_CHUNK_SIZE: usize = 512;
+pub(crate) const STDIN_CHUNK_DELAY: Duration = Duration::from_millis(20);
+
+const PREVIEW_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteInfo {
+    pub bytes: usize,
+    pub lines: usize,
+    pub bracketed: bool,
+    pub preview: String,
+}
+
+impl PasteInfo {
+    // More than one line would run at once when it reaches the shell
+    pub fn is_multiline(&self) -> bool {
+        self.lines > 1
+    }
+}
+
+// Classify terminal input, keystrokes arrive one at a time so anything longer
+// with a line break or bracketed paste markers is treated as a paste
+pub(crate) fn detect_paste(data: &str) -> Option<PasteInfo> {
+    let bracketed = data.contains(BRACKETED_PASTE_START);
+    let has_break = data.contains(['\r', '\n']);
+    if !bracketed && (data.chars().count() < 2 || !has_break) && data.len() < STDIN_CHUNK_SIZE {
+        return None;
+    }
+
+    let content = data
+        .replace(BRACKETED_PASTE_START, "")
+        .replace(BRACKETED_PASTE_END, "");
+    let lines = content
+        .trim_end_matches(['\r', '\n'])
+        .split(['\r', '\n'])
+        .filter(|line| !line.is_empty())
+        .count();
+
+    Some(PasteInfo {
+        bytes: data.len(),
+        lines,
+        bracketed,
+        preview: content.chars().take(PREVIEW_CHARS).collect(),
+    })
+}
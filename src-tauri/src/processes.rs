@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{command, Manager, Window};
+
+use crate::audit;
+use crate::confirm::{self, ConfirmationRequest};
+use crate::exec::{run_exec_command, ExecTarget};
+
+const PS_TIMEOUT_SECS: u64 = 60;
+const SIGNAL_ACTION: &str = "send_signal";
+
+const PROC_MARKER: &str = "__ECS_PROC__";
+const HEADER_MARKER: &str = "__ECS_HDR__";
+const PASSWD_MARKER: &str = "__ECS_PW__";
+
+const SIGNALS: &[&str] = &[
+    "HUP", "INT", "QUIT", "KILL", "USR1", "USR2", "TERM", "CONT", "STOP",
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteProcess {
+    pid: u32,
+    ppid: u32,
+    user: String,
+    // Percent of one CPU, averaged over the process lifetime
+    cpu: Option<f64>,
+    // Resident memory in KiB
+    memory_kb: Option<u64>,
+    // Seconds since the epoch
+    start_time: Option<i64>,
+    command: String,
+}
+
+// procps ps, busybox ps lacks pcpu/etimes so it falls through to /proc
+const PS_SCRIPT: &str = "ps -eo pid=,ppid=,user=,pcpu=,rss=,etimes=,args=";
+
+fn proc_script() -> String {
+    format!(
+        r#"hz=$(getconf CLK_TCK 2>/dev/null || echo 100)
+echo "{hdr}$hz $(cut -d' ' -f1 /proc/uptime) $(awk '/^btime/{{print $2}}' /proc/stat)"
+for d in /proc/[0-9]*; do
+  s=$(cat "$d/stat" 2>/dev/null) || continue
+  u=$(awk '/^Uid:/{{print $2}}' "$d/status" 2>/dev/null)
+  r=$(awk '/^VmRSS:/{{print $2}}' "$d/status" 2>/dev/null)
+  c=$(tr '\0' ' ' < "$d/cmdline" 2>/dev/null)
+  printf '{proc}%s\037%s\037%s\037%s\n' "$u" "$r" "$c" "$s"
+done
+sed 's/^/{pw}/' /etc/passwd 2>/dev/null
+true"#,
+        hdr = HEADER_MARKER,
+        proc = PROC_MARKER,
+        pw = PASSWD_MARKER,
+    )
+}
+
+fn parse_ps(output: &str) -> Vec<RemoteProcess> {
+    let now = chrono::Utc::now().timestamp();
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            let pid = cols.next()?.parse().ok()?;
+            let ppid = cols.next()?.parse().ok()?;
+            let user = cols.next()?.to_string();
+            let cpu = cols.next()?.parse().ok();
+            let memory_kb = cols.next()?.parse().ok();
+            let elapsed: Option<i64> = cols.next()?.parse().ok();
+            let command = cols.collect::<Vec<_>>().join(" ");
+            Some(RemoteProcess {
+                pid,
+                ppid,
+                user,
+                cpu,
+                memory_kb,
+                start_time: elapsed.map(|e| now - e),
+                command,
+            })
+        })
+        .collect()
+}
+
+fn parse_proc(output: &str) -> Vec<RemoteProcess> {
+    let mut hz = 100.0;
+    let mut uptime = None;
+    let mut boot_time = None;
+    let mut users = HashMap::new();
+    let mut raw = Vec::new();
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix(HEADER_MARKER) {
+            let mut parts = header.split_whitespace();
+            hz = parts.next().and_then(|s| s.parse().ok()).unwrap_or(100.0);
+            uptime = parts.next().and_then(|s| s.parse::<f64>().ok());
+            boot_time = parts.next().and_then(|s| s.parse::<i64>().ok());
+        } else if let Some(entry) = line.strip_prefix(PASSWD_MARKER) {
+            let fields: Vec<&str> = entry.split(':').collect();
+            if fields.len() > 2 {
+                users.insert(fields[2].to_string(), fields[0].to_string());
+            }
+        } else if let Some(entry) = line.strip_prefix(PROC_MARKER) {
+            raw.push(entry);
+        }
+    }
+
+    raw.into_iter()
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(4, '\x1f');
+            let uid = parts.next()?;
+            let rss = parts.next()?;
+            let cmdline = parts.next()?.trim();
+            let stat = parts.next()?;
+
+            // pid (comm) state ppid ... the command name may itself contain spaces or parens
+            let open = stat.find('(')?;
+            let close = stat.rfind(')')?;
+            let pid = stat[..open].trim().parse().ok()?;
+            let comm = &stat[open + 1..close];
+            let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+            let ppid = fields.get(1)?.parse().ok()?;
+            let ticks = |i: usize| fields.get(i).and_then(|s| s.parse::<f64>().ok());
+
+            let started = ticks(19).map(|t| t / hz);
+            let cpu = match (ticks(11), ticks(12), started, uptime) {
+                (Some(utime), Some(stime), Some(started), Some(uptime)) if uptime > started => {
+                    Some(((utime + stime) / hz) / (uptime - started) * 100.0)
+                }
+                _ => None,
+            };
+
+            Some(RemoteProcess {
+                pid,
+                ppid,
+                user: users.get(uid).cloned().unwrap_or_else(|| uid.to_string()),
+                cpu: cpu.map(|c| (c * 10.0).round() / 10.0),
+                memory_kb: rss.trim().parse().ok(),
+                start_time: match (boot_time, started) {
+                    (Some(boot), Some(started)) => Some(boot + started as i64),
+                    _ => None,
+                },
+                command: if cmdline.is_empty() {
+                    format!("[{}]", comm)
+                } else {
+                    cmdline.to_string()
+                },
+            })
+        })
+        .collect()
+}
+
+#[command]
+pub async fn list_remote_processes(target: ExecTarget) -> Result<Vec<RemoteProcess>, String> {
+    let timeout = Duration::from_secs(PS_TIMEOUT_SECS);
+
+    let out = run_exec_command(&target, PS_SCRIPT, timeout).await?;
+    if out.exit_code == Some(0) {
+        let processes = parse_ps(&out.output);
+        if !processes.is_empty() {
+            return Ok(processes);
+        }
+    }
+
+    let out = run_exec_command(&target, &proc_script(), timeout).await?;
+    let processes = parse_proc(&out.output);
+    if processes.is_empty() {
+        return Err(format!("Failed to read processes: {}", out.output.trim()));
+    }
+    Ok(processes)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignalRequest {
+    target: ExecTarget,
+    pid: u32,
+    signal: String,
+}
+
+// Accept signal names with or without the SIG prefix, or plain numbers
+fn normalize_signal(signal: &str) -> Result<String, String> {
+    let upper = signal.trim().to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    if SIGNALS.contains(&name) {
+        return Ok(name.to_string());
+    }
+    match name.parse::<u32>() {
+        Ok(n) if (1..=64).contains(&n) => Ok(n.to_string()),
+        _ => Err(format!("Unsupported signal: {}", signal)),
+    }
+}
+
+#[command]
+pub fn request_signal_confirmation(
+    target: ExecTarget,
+    pid: u32,
+    signal: String,
+) -> Result<ConfirmationRequest, String> {
+    let signal = normalize_signal(&signal)?;
+    let summary = format!(
+        "Send SIG{} to pid {} in container {} of task {}",
+        signal, pid, target.container, target.task
+    );
+    let payload = serde_json::to_value(SignalRequest {
+        target,
+        pid,
+        signal,
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(confirm::issue(SIGNAL_ACTION, summary, payload))
+}
+
+#[command]
+pub async fn send_remote_signal(window: Window, token: String) -> Result<String, String> {
+    let request: SignalRequest = serde_json::from_value(confirm::redeem(SIGNAL_ACTION, &token)?)
+        .map_err(|e| e.to_string())?;
+
+    let signal_arg = if request.signal.chars().all(|c| c.is_ascii_digit()) {
+        format!("-{}", request.signal)
+    } else {
+        format!("-s {}", request.signal)
+    };
+    let out = run_exec_command(
+        &request.target,
+        &format!("kill {} {}", signal_arg, request.pid),
+        Duration::from_secs(PS_TIMEOUT_SECS),
+    )
+    .await?;
+
+    audit::record(
+        window.app_handle(),
+        SIGNAL_ACTION,
+        json!({
+            "task": request.target.task,
+            "container": request.target.container,
+            "pid": request.pid,
+            "signal": request.signal,
+            "exitCode": out.exit_code,
+        }),
+    );
+
+    if out.exit_code != Some(0) {
+        return Err(format!("kill failed: {}", out.output.trim()));
+    }
+    Ok(out.output)
+}
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{command, AppHandle, Manager, Window};
+
+use crate::aws::describe_running_tasks;
+use crate::exec::ExecTarget;
+use crate::storage;
+use crate::terminal::{self, SessionTransport};
+
+const PROFILES_STORE: &str = ".connection-profiles.dat";
+const PROFILES_KEY: &str = "profiles";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskSelection {
+    // Most recently started running task
+    #[default]
+    Newest,
+    // Longest running task
+    Oldest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfile {
+    #[serde(default)]
+    id: String,
+    name: String,
+    aws_profile: String,
+    region: String,
+    cluster: String,
+    // Pick a task from this service, otherwise from any task in the cluster
+    #[serde(default)]
+    service: Option<String>,
+    // Container name, defaults to the first container with ECS Exec available
+    #[serde(default)]
+    container: Option<String>,
+    #[serde(default)]
+    task_selection: TaskSelection,
+    // Detected from the container when unset
+    #[serde(default)]
+    shell_cmd: Option<String>,
+    // Typed into the shell in order, each once the prompt appears
+    #[serde(default)]
+    init_commands: Vec<String>,
+    // Sessions opened from this profile hold multi-line pastes for confirmation
+    #[serde(default)]
+    production: bool,
+    // How sessions from this profile reach the container, the plugin unless set
+    #[serde(default)]
+    transport: SessionTransport,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedConnection {
+    profile: String,
+    region: String,
+    cluster: String,
+    task: String,
+    container: String,
+    shell_cmd: String,
+}
+
+fn load_profiles(app: &AppHandle) -> Result<Vec<ConnectionProfile>, String> {
+    storage::load(app, PROFILES_STORE, PROFILES_KEY)
+}
+
+fn save_profiles(app: &AppHandle, profiles: &[ConnectionProfile]) -> Result<(), String> {
+    storage::save(app, PROFILES_STORE, PROFILES_KEY, &profiles)
+}
+
+fn exec_agent_running(container: &Value) -> bool {
+    container["managedAgents"]
+        .as_array()
+        .map(|agents| {
+            agents.iter().any(|a| {
+                a["name"].as_str() == Some("ExecuteCommandAgent")
+                    && a["lastStatus"].as_str() == Some("RUNNING")
+            })
+        })
+        .unwrap_or(false)
+}
+
+// Apply a profile's selection rules to the tasks currently running
+async fn resolve_target(profile: &ConnectionProfile) -> Result<ExecTarget, String> {
+    let mut tasks = describe_running_tasks(
+        &profile.aws_profile,
+        &profile.region,
+        &profile.cluster,
+        profile.service.as_deref(),
+    )
+    .await?;
+    if tasks.is_empty() {
+        return Err("No running tasks match this connection profile".to_string());
+    }
+
+    tasks.sort_by_key(|t| t["startedAt"].as_str().unwrap_or("").to_string());
+    if let TaskSelection::Newest = profile.task_selection {
+        tasks.reverse();
+    }
+
+    for task in tasks {
+        let Some(task_arn) = task["taskArn"].as_str() else { continue };
+        let containers = task["containers"].as_array().cloned().unwrap_or_default();
+
+        let container = match profile.container {
+            Some(ref name) => containers
+                .iter()
+                .find(|c| c["name"].as_str() == Some(name.as_str()))
+                .and_then(|c| c["name"].as_str()),
+            None => containers
+                .iter()
+                .find(|c| exec_agent_running(c))
+                .and_then(|c| c["name"].as_str()),
+        };
+
+        if let Some(container) = container {
+            return Ok(ExecTarget {
+                profile: profile.aws_profile.clone(),
+                region: profile.region.clone(),
+                cluster: profile.cluster.clone(),
+                task: task_arn.to_string(),
+                container: container.to_string(),
+            });
+        }
+    }
+
+    Err("No running task has a matching container with ECS Exec enabled".to_string())
+}
+
+// Whether a production connection profile covers this cluster or service.
+// Without a service, any production profile on the cluster counts.
+pub(crate) fn is_production(
+    app: &AppHandle,
+    aws_profile: &str,
+    region: &str,
+    cluster: &str,
+    service: Option<&str>,
+) -> bool {
+    match load_profiles(app) {
+        Ok(profiles) => covered_by_production(&profiles, aws_profile, region, cluster, service),
+        Err(e) => {
+            // Fail closed, an unreadable store must not lift the protection
+            eprintln!("[DEBUG] Failed to load connection profiles: {}", e);
+            true
+        }
+    }
+}
+
+fn covered_by_production(
+    profiles: &[ConnectionProfile],
+    aws_profile: &str,
+    region: &str,
+    cluster: &str,
+    service: Option<&str>,
+) -> bool {
+    // Clusters and services may be given by name or ARN
+    let name = |arn: &str| arn.rsplit('/').next().unwrap_or(arn).to_string();
+    profiles.iter().any(|p| {
+        p.production
+            && p.aws_profile == aws_profile
+            && p.region == region
+            && name(&p.cluster) == name(cluster)
+            && match (&p.service, service) {
+                (Some(covered), Some(service)) => name(covered) == name(service),
+                _ => true,
+            }
+    })
+}
+
+#[command]
+pub fn list_connection_profiles(window: Window) -> Result<Vec<ConnectionProfile>, String> {
+    load_profiles(window.app_handle())
+}
+
+#[command]
+pub fn save_connection_profile(
+    window: Window,
+    mut profile: ConnectionProfile,
+) -> Result<ConnectionProfile, String> {
+    let app = window.app_handle();
+    let mut profiles = load_profiles(app)?;
+
+    if profile.id.is_empty() {
+        profile.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    match profiles.iter_mut().find(|p| p.id == profile.id) {
+        Some(existing) => *existing = profile.clone(),
+        None => profiles.push(profile.clone()),
+    }
+
+    save_profiles(app, &profiles)?;
+    Ok(profile)
+}
+
+#[command]
+pub fn delete_connection_profile(window: Window, profile_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let mut profiles = load_profiles(app)?;
+    profiles.retain(|p| p.id != profile_id);
+    save_profiles(app, &profiles)
+}
+
+#[command]
+pub async fn connect_with_profile(
+    window: Window,
+    session_id: String,
+    profile_id: String,
+) -> Result<ResolvedConnection, String> {
+    let profile = load_profiles(window.app_handle())?
+        .into_iter()
+        .find(|p| p.id == profile_id)
+        .ok_or("Connection profile not found")?;
+
+    let target = resolve_target(&profile).await?;
+    let mut resolved = ResolvedConnection {
+        profile: target.profile.clone(),
+        region: target.region.clone(),
+        cluster: target.cluster.clone(),
+        task: target.task.clone(),
+        container: target.container.clone(),
+        shell_cmd: String::new(),
+    };
+
+    let session = terminal::spawn_exec_session(
+        window,
+        session_id,
+        target,
+        profile.shell_cmd,
+        profile.init_commands,
+        profile.production,
+        profile.transport,
+    )
+    .await?;
+    resolved.shell_cmd = session.shell_cmd;
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(cluster: &str, service: Option<&str>, production: bool) -> ConnectionProfile {
+        ConnectionProfile {
+            id: String::new(),
+            name: "profile".to_string(),
+            aws_profile: "prod-account".to_string(),
+            region: "eu-west-1".to_string(),
+            cluster: cluster.to_string(),
+            service: service.map(|s| s.to_string()),
+            container: None,
+            task_selection: TaskSelection::default(),
+            shell_cmd: None,
+            init_commands: Vec::new(),
+            production,
+            transport: SessionTransport::default(),
+        }
+    }
+
+    #[test]
+    fn production_matches_clusters_by_name_or_arn() {
+        let profiles = vec![profile("main", None, true)];
+        let arn = "arn:aws:ecs:eu-west-1:123456789012:cluster/main";
+        assert!(covered_by_production(
+            &profiles,
+            "prod-account",
+            "eu-west-1",
+            arn,
+            Some("api")
+        ));
+        assert!(covered_by_production(
+            &profiles,
+            "prod-account",
+            "eu-west-1",
+            "main",
+            None
+        ));
+        assert!(!covered_by_production(
+            &profiles,
+            "prod-account",
+            "us-east-1",
+            "main",
+            None
+        ));
+        assert!(!covered_by_production(
+            &profiles,
+            "dev-account",
+            "eu-west-1",
+            "main",
+            None
+        ));
+        assert!(!covered_by_production(
+            &profiles,
+            "prod-account",
+            "eu-west-1",
+            "other",
+            None
+        ));
+    }
+
+    #[test]
+    fn production_service_profiles_cover_only_their_service() {
+        let profiles = vec![
+            profile("main", Some("api"), true),
+            profile("main", Some("worker"), false),
+        ];
+        let service_arn = "arn:aws:ecs:eu-west-1:123456789012:service/main/api";
+        assert!(covered_by_production(
+            &profiles,
+            "prod-account",
+            "eu-west-1",
+            "main",
+            Some(service_arn)
+        ));
+        assert!(!covered_by_production(
+            &profiles,
+            "prod-account",
+            "eu-west-1",
+            "main",
+            Some("worker")
+        ));
+        // Without a service anything on the cluster could be affected
+        assert!(covered_by_production(
+            &profiles,
+            "prod-account",
+            "eu-west-1",
+            "main",
+            None
+        ));
+    }
+}
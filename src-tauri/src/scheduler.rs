@@ -0,0 +1,309 @@
+use chrono::{DateTime, Local, TimeZone, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, Window};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::aws::list_running_tasks;
+use crate::exec::{run_exec_command, ExecTarget};
+use crate::storage;
+
+const JOBS_STORE: &str = ".scheduled-jobs.dat";
+const JOBS_KEY: &str = "jobs";
+const HISTORY_KEY: &str = "history";
+const MAX_HISTORY_PER_JOB: usize = 50;
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+const JOB_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Guards read-modify-write of the jobs store between commands and the scheduler loop
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+static RUNNING_JOBS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledJob {
+    #[serde(default)]
+    id: String,
+    name: String,
+    profile: String,
+    region: String,
+    cluster: String,
+    // Run on a running task of this service, or on the given task
+    #[serde(default)]
+    service: Option<String>,
+    #[serde(default)]
+    task: Option<String>,
+    container: String,
+    command: String,
+    // Standard five-field cron expression, evaluated in local time
+    cron: String,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    created_at: i64,
+    #[serde(default)]
+    last_run_at: Option<i64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRun {
+    started_at: i64,
+    duration_ms: u64,
+    task: Option<String>,
+    output: Option<String>,
+    exit_code: Option<i32>,
+    error: Option<String>,
+    // Output differs from the previous run
+    changed: bool,
+    failed: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobRunEvent {
+    job_id: String,
+    run: JobRun,
+}
+
+// Cron expressions are written with five fields, the cron crate also wants seconds
+fn parse_cron(expr: &str) -> Result<Schedule, String> {
+    let expr = expr.trim();
+    let full = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    Schedule::from_str(&full).map_err(|e| format!("Invalid cron expression '{}': {}", expr, e))
+}
+
+fn is_due(job: &ScheduledJob, now: DateTime<Local>) -> bool {
+    let Ok(schedule) = parse_cron(&job.cron) else {
+        return false;
+    };
+    let since = job.last_run_at.unwrap_or(job.created_at);
+    let Some(since) = Local.timestamp_millis_opt(since).single() else {
+        return false;
+    };
+    schedule.after(&since).next().is_some_and(|next| next <= now)
+}
+
+fn load_jobs(app: &AppHandle) -> Result<Vec<ScheduledJob>, String> {
+    storage::load(app, JOBS_STORE, JOBS_KEY)
+}
+
+fn load_history(app: &AppHandle) -> Result<HashMap<String, Vec<JobRun>>, String> {
+    storage::load(app, JOBS_STORE, HISTORY_KEY)
+}
+
+async fn resolve_task(job: &ScheduledJob) -> Result<String, String> {
+    if let Some(ref task) = job.task {
+        return Ok(task.clone());
+    }
+    let service = job.service.as_deref().ok_or("Job has neither a service nor a task")?;
+    list_running_tasks(&job.profile, &job.region, &job.cluster, Some(service))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No running tasks for service {}", service))
+}
+
+async fn run_job(app: &AppHandle, job: &ScheduledJob) -> Result<JobRun, String> {
+    {
+        let mut running = RUNNING_JOBS.lock().unwrap();
+        if !running.get_or_insert_with(HashSet::new).insert(job.id.clone()) {
+            return Err("Job is already running".to_string());
+        }
+    }
+
+    let started_at = Utc::now().timestamp_millis();
+    let timer = Instant::now();
+
+    let task = resolve_task(job).await;
+    let outcome = match task {
+        Ok(ref task) => {
+            let target = ExecTarget {
+                profile: job.profile.clone(),
+                region: job.region.clone(),
+                cluster: job.cluster.clone(),
+                task: task.clone(),
+                container: job.container.clone(),
+            };
+            run_exec_command(&target, &job.command, JOB_TIMEOUT).await
+        }
+        Err(ref e) => Err(e.clone()),
+    };
+
+    if let Some(ref mut running) = *RUNNING_JOBS.lock().unwrap() {
+        running.remove(&job.id);
+    }
+
+    let (output, exit_code, error) = match outcome {
+        Ok(out) => (Some(out.output), out.exit_code, None),
+        Err(e) => (None, None, Some(e)),
+    };
+    let failed = error.is_some() || exit_code.is_some_and(|code| code != 0);
+
+    let run = {
+        let _guard = STORE_LOCK.lock().unwrap();
+
+        let mut history = load_history(app)?;
+        let runs = history.entry(job.id.clone()).or_default();
+        let changed = match runs.iter().rev().find(|r| r.output.is_some()) {
+            Some(previous) => output.is_some() && previous.output != output,
+            None => false,
+        };
+
+        let run = JobRun {
+            started_at,
+            duration_ms: timer.elapsed().as_millis() as u64,
+            task: task.ok(),
+            output,
+            exit_code,
+            error,
+            changed,
+            failed,
+        };
+
+        runs.push(run.clone());
+        if runs.len() > MAX_HISTORY_PER_JOB {
+            let excess = runs.len() - MAX_HISTORY_PER_JOB;
+            runs.drain(..excess);
+        }
+        storage::save(app, JOBS_STORE, HISTORY_KEY, &history)?;
+
+        let mut jobs = load_jobs(app)?;
+        if let Some(stored) = jobs.iter_mut().find(|j| j.id == job.id) {
+            stored.last_run_at = Some(started_at);
+        }
+        storage::save(app, JOBS_STORE, JOBS_KEY, &jobs)?;
+
+        run
+    };
+
+    if run.failed || run.changed {
+        let body = if let Some(ref error) = run.error {
+            error.clone()
+        } else if run.failed {
+            format!("Exited with code {}", run.exit_code.unwrap_or(-1))
+        } else {
+            "Output changed since the previous run".to_string()
+        };
+        let _ = app
+            .notification()
+            .builder()
+            .title(&job.name)
+            .body(body)
+            .show();
+    }
+
+    let _ = app.emit(
+        "scheduler:run",
+        JobRunEvent {
+            job_id: job.id.clone(),
+            run: run.clone(),
+        },
+    );
+
+    Ok(run)
+}
+
+// Check for due jobs in the background for as long as the app runs
+pub(crate) fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let jobs = match load_jobs(&app) {
+                Ok(jobs) => jobs,
+                Err(e) => {
+                    eprintln!("[DEBUG] Scheduler failed to load jobs: {}", e);
+                    continue;
+                }
+            };
+
+            let now = Local::now();
+            for job in jobs.into_iter().filter(|j| j.enabled && is_due(j, now)) {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = run_job(&app, &job).await {
+                        eprintln!("[DEBUG] Scheduled job {} failed: {}", job.id, e);
+                    }
+                });
+            }
+        }
+    });
+}
+
+#[command]
+pub fn list_scheduled_jobs(window: Window) -> Result<Vec<ScheduledJob>, String> {
+    load_jobs(window.app_handle())
+}
+
+#[command]
+pub fn save_scheduled_job(window: Window, mut job: ScheduledJob) -> Result<ScheduledJob, String> {
+    parse_cron(&job.cron)?;
+    if job.service.is_none() && job.task.is_none() {
+        return Err("A job needs a service or a task to run on".to_string());
+    }
+
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+    let mut jobs = load_jobs(app)?;
+
+    if job.id.is_empty() {
+        job.id = uuid::Uuid::new_v4().to_string();
+        job.created_at = Utc::now().timestamp_millis();
+    }
+
+    match jobs.iter_mut().find(|j| j.id == job.id) {
+        Some(existing) => {
+            job.created_at = existing.created_at;
+            job.last_run_at = existing.last_run_at;
+            *existing = job.clone();
+        }
+        None => jobs.push(job.clone()),
+    }
+
+    storage::save(app, JOBS_STORE, JOBS_KEY, &jobs)?;
+    Ok(job)
+}
+
+#[command]
+pub fn delete_scheduled_job(window: Window, job_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+
+    let mut jobs = load_jobs(app)?;
+    jobs.retain(|j| j.id != job_id);
+    storage::save(app, JOBS_STORE, JOBS_KEY, &jobs)?;
+
+    let mut history = load_history(app)?;
+    history.remove(&job_id);
+    storage::save(app, JOBS_STORE, HISTORY_KEY, &history)
+}
+
+#[command]
+pub fn get_job_history(window: Window, job_id: String) -> Result<Vec<JobRun>, String> {
+    let mut history = load_history(window.app_handle())?;
+    Ok(history.remove(&job_id).unwrap_or_default())
+}
+
+#[command]
+pub async fn run_scheduled_job_now(window: Window, job_id: String) -> Result<JobRun, String> {
+    let app = window.app_handle().clone();
+    let job = load_jobs(&app)?
+        .into_iter()
+        .find(|j| j.id == job_id)
+        .ok_or("Scheduled job not found")?;
+    run_job(&app, &job).await
+}
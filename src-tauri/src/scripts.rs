@@ -0,0 +1,198 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::command;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+
+use crate::terminal::{self, strip_ansi};
+
+const DEFAULT_STEP_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ScriptStep {
+    // Type text into the session, followed by Enter unless disabled
+    #[serde(rename_all = "camelCase")]
+    Send {
+        text: String,
+        #[serde(default = "default_true")]
+        enter: bool,
+    },
+    // Wait for output matching the pattern
+    #[serde(rename_all = "camelCase")]
+    Expect {
+        pattern: String,
+        timeout_secs: Option<u64>,
+    },
+    // Wait for the pattern and store its first group (or the whole match) under a name
+    #[serde(rename_all = "camelCase")]
+    Capture {
+        name: String,
+        pattern: String,
+        timeout_secs: Option<u64>,
+    },
+    // Change the timeout used by later expect and capture steps
+    #[serde(rename_all = "camelCase")]
+    Timeout { secs: u64 },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptResult {
+    completed: bool,
+    steps_run: usize,
+    failed_step: Option<usize>,
+    error: Option<String>,
+    captures: HashMap<String, String>,
+    // Output seen while the script ran, escape sequences removed
+    transcript: String,
+}
+
+// Output received since the script started, with a cursor past the last match
+struct OutputBuffer {
+    text: String,
+    cursor: usize,
+}
+
+impl OutputBuffer {
+    fn find(&mut self, regex: &Regex) -> Option<String> {
+        let caps = regex.captures(&self.text[self.cursor..])?;
+        let whole = caps.get(0)?;
+        let value = caps.get(1).unwrap_or(whole).as_str().to_string();
+        self.cursor += whole.end();
+        Some(value)
+    }
+}
+
+async fn wait_for(
+    output: &mut tokio::sync::broadcast::Receiver<String>,
+    buffer: &mut OutputBuffer,
+    regex: &Regex,
+    timeout: Duration,
+) -> Result<String, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(value) = buffer.find(regex) {
+            return Ok(value);
+        }
+        match tokio::time::timeout_at(deadline, output.recv()).await {
+            Ok(Ok(chunk)) => buffer.text.push_str(&strip_ansi(&chunk)),
+            // The pattern may have been in what was dropped
+            Ok(Err(RecvError::Lagged(missed))) => {
+                return Err(format!(
+                    "Output lagged, {} chunks were lost while waiting for /{}/",
+                    missed,
+                    regex.as_str()
+                ))
+            }
+            Ok(Err(RecvError::Closed)) => return Err("Session ended".to_string()),
+            Err(_) => {
+                return Err(format!(
+                    "Timed out after {}s waiting for /{}/",
+                    timeout.as_secs(),
+                    regex.as_str()
+                ))
+            }
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+// Run a script against a live session. The session stays open afterwards so
+// the user can carry on in it by hand.
+#[command]
+pub async fn run_session_script(
+    session_id: String,
+    steps: Vec<ScriptStep>,
+) -> Result<ScriptResult, String> {
+    // Validate every pattern before touching the session
+    let mut patterns = HashMap::new();
+    for step in &steps {
+        if let ScriptStep::Expect { pattern, .. } | ScriptStep::Capture { pattern, .. } = step {
+            if !patterns.contains_key(pattern) {
+                patterns.insert(pattern.clone(), compile(pattern)?);
+            }
+        }
+    }
+
+    let mut output = terminal::subscribe_output(&session_id).ok_or("Session not found")?;
+    let mut buffer = OutputBuffer {
+        text: String::new(),
+        cursor: 0,
+    };
+    let mut timeout = Duration::from_secs(DEFAULT_STEP_TIMEOUT_SECS);
+    let mut captures = HashMap::new();
+    let mut failure = None;
+    let mut steps_run = 0;
+
+    for (index, step) in steps.iter().enumerate() {
+        let outcome = match step {
+            ScriptStep::Send { text, enter } => {
+                let mut data = text.clone();
+                if *enter {
+                    data.push('\r');
+                }
+                terminal::send_to_session(&session_id, data.into_bytes())
+            }
+            ScriptStep::Expect {
+                pattern,
+                timeout_secs,
+            } => {
+                let step_timeout = timeout_secs.map(Duration::from_secs).unwrap_or(timeout);
+                wait_for(&mut output, &mut buffer, &patterns[pattern], step_timeout)
+                    .await
+                    .map(|_| ())
+            }
+            ScriptStep::Capture {
+                name,
+                pattern,
+                timeout_secs,
+            } => {
+                let step_timeout = timeout_secs.map(Duration::from_secs).unwrap_or(timeout);
+                wait_for(&mut output, &mut buffer, &patterns[pattern], step_timeout)
+                    .await
+                    .map(|value| {
+                        captures.insert(name.clone(), value.trim().to_string());
+                    })
+            }
+            ScriptStep::Timeout { secs } => {
+                timeout = Duration::from_secs(*secs);
+                Ok(())
+            }
+        };
+
+        if let Err(e) = outcome {
+            failure = Some((index, e));
+            break;
+        }
+        steps_run += 1;
+    }
+
+    // Pick up anything printed after the last expectation for the transcript
+    while let Ok(chunk) = output.try_recv() {
+        buffer.text.push_str(&strip_ansi(&chunk));
+    }
+
+    let (failed_step, error) = match failure {
+        Some((index, e)) => (Some(index), Some(e)),
+        None => (None, None),
+    };
+
+    Ok(ScriptResult {
+        completed: failed_step.is_none(),
+        steps_run,
+        failed_step,
+        error,
+        captures,
+        transcript: buffer.text,
+    })
+}
@@ -0,0 +1,260 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use aws_sdk_ecs::primitives::DateTime;
+use serde::Serialize;
+use tauri::command;
+
+use crate::ecs_api;
+
+// DescribeServices accepts at most this many services per call
+const DESCRIBE_BATCH: usize = 10;
+// The API keeps the last 100 events, the newest first
+const MAX_EVENTS: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDetails {
+    pub service_name: String,
+    pub service_arn: String,
+    pub cluster_arn: Option<String>,
+    // ACTIVE, DRAINING or INACTIVE
+    pub status: Option<String>,
+    pub desired_count: i32,
+    pub running_count: i32,
+    pub pending_count: i32,
+    // None when the service runs on a capacity provider strategy
+    pub launch_type: Option<String>,
+    pub capacity_providers: Vec<String>,
+    pub platform_version: Option<String>,
+    pub task_definition: Option<String>,
+    pub deployments: Vec<ServiceDeployment>,
+    pub events: Vec<ServiceEvent>,
+    pub load_balancers: Vec<ServiceLoadBalancer>,
+    pub network_configuration: Option<ServiceNetworkConfiguration>,
+    pub enable_execute_command: bool,
+    // One completed deployment with every desired task running
+    pub steady: bool,
+    pub created_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceFailure {
+    // The service as requested, or its ARN
+    pub service: String,
+    // e.g. MISSING
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceOverview {
+    pub services: Vec<ServiceDetails>,
+    // Services that could not be described, the rest are still returned
+    pub failures: Vec<ServiceFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDeployment {
+    pub id: String,
+    // PRIMARY, ACTIVE or INACTIVE
+    pub status: Option<String>,
+    pub task_definition: Option<String>,
+    pub desired_count: i32,
+    pub running_count: i32,
+    pub pending_count: i32,
+    pub failed_tasks: i32,
+    // IN_PROGRESS, COMPLETED or FAILED
+    pub rollout_state: Option<String>,
+    pub rollout_state_reason: Option<String>,
+    pub launch_type: Option<String>,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEvent {
+    pub id: String,
+    pub created_at: Option<i64>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceLoadBalancer {
+    pub target_group_arn: Option<String>,
+    // Classic load balancers only
+    pub load_balancer_name: Option<String>,
+    pub container_name: Option<String>,
+    pub container_port: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceNetworkConfiguration {
+    pub subnets: Vec<String>,
+    pub security_groups: Vec<String>,
+    pub assign_public_ip: bool,
+}
+
+fn millis(time: Option<&DateTime>) -> Option<i64> {
+    time.and_then(|t| t.to_millis().ok())
+}
+
+fn owned(value: Option<&str>) -> Option<String> {
+    value.map(|s| s.to_string())
+}
+
+fn service_details(service: &aws_sdk_ecs::types::Service) -> ServiceDetails {
+    let deployments: Vec<ServiceDeployment> = service
+        .deployments()
+        .iter()
+        .map(|d| ServiceDeployment {
+            id: d.id().unwrap_or_default().to_string(),
+            status: owned(d.status()),
+            task_definition: owned(d.task_definition()),
+            desired_count: d.desired_count(),
+            running_count: d.running_count(),
+            pending_count: d.pending_count(),
+            failed_tasks: d.failed_tasks(),
+            rollout_state: d.rollout_state().map(|s| s.as_str().to_string()),
+            rollout_state_reason: owned(d.rollout_state_reason()),
+            launch_type: d.launch_type().map(|l| l.as_str().to_string()),
+            created_at: millis(d.created_at()),
+            updated_at: millis(d.updated_at()),
+        })
+        .collect();
+
+    // Services created before rollout states existed report none once settled
+    let steady = deployments.len() == 1
+        && deployments[0]
+            .rollout_state
+            .as_deref()
+            .unwrap_or("COMPLETED")
+            == "COMPLETED"
+        && service.running_count() == service.desired_count()
+        && service.pending_count() == 0;
+
+    ServiceDetails {
+        service_name: service.service_name().unwrap_or_default().to_string(),
+        service_arn: service.service_arn().unwrap_or_default().to_string(),
+        cluster_arn: owned(service.cluster_arn()),
+        status: owned(service.status()),
+        desired_count: service.desired_count(),
+        running_count: service.running_count(),
+        pending_count: service.pending_count(),
+        launch_type: service.launch_type().map(|l| l.as_str().to_string()),
+        capacity_providers: service
+            .capacity_provider_strategy()
+            .iter()
+            .map(|c| c.capacity_provider().to_string())
+            .collect(),
+        platform_version: owned(service.platform_version()),
+        task_definition: owned(service.task_definition()),
+        deployments,
+        events: service
+            .events()
+            .iter()
+            .take(MAX_EVENTS)
+            .map(|e| ServiceEvent {
+                id: e.id().unwrap_or_default().to_string(),
+                created_at: millis(e.created_at()),
+                message: e.message().unwrap_or_default().to_string(),
+            })
+            .collect(),
+        load_balancers: service
+            .load_balancers()
+            .iter()
+            .map(|lb| ServiceLoadBalancer {
+                target_group_arn: owned(lb.target_group_arn()),
+                load_balancer_name: owned(lb.load_balancer_name()),
+                container_name: owned(lb.container_name()),
+                container_port: lb.container_port(),
+            })
+            .collect(),
+        network_configuration: service
+            .network_configuration()
+            .and_then(|n| n.awsvpc_configuration())
+            .map(|vpc| ServiceNetworkConfiguration {
+                subnets: vpc.subnets().to_vec(),
+                security_groups: vpc.security_groups().to_vec(),
+                assign_public_ip: vpc.assign_public_ip().map(|a| a.as_str()) == Some("ENABLED"),
+            }),
+        enable_execute_command: service.enable_execute_command(),
+        steady,
+        created_at: millis(service.created_at()),
+    }
+}
+
+// Describe services by name or ARN, in the order requested
+pub(crate) async fn describe_services(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    services: &[String],
+) -> Result<ServiceOverview, String> {
+    let client = ecs_api::client(profile, region).await;
+    let mut details = Vec::with_capacity(services.len());
+    let mut failures = Vec::new();
+
+    for batch in services.chunks(DESCRIBE_BATCH) {
+        let out = client
+            .describe_services()
+            .cluster(cluster)
+            .set_services(Some(batch.to_vec()))
+            .send()
+            .await
+            .map_err(|e| format!("DescribeServices failed: {}", DisplayErrorContext(e)))?;
+
+        failures.extend(out.failures().iter().map(|f| ServiceFailure {
+            service: f.arn().unwrap_or_default().to_string(),
+            reason: f.reason().unwrap_or("unknown reason").to_string(),
+        }));
+        details.extend(out.services().iter().map(service_details));
+    }
+
+    // DescribeServices does not keep the order it was asked in
+    let position = |s: &ServiceDetails| {
+        services
+            .iter()
+            .position(|requested| *requested == s.service_arn || *requested == s.service_name)
+            .unwrap_or(usize::MAX)
+    };
+    details.sort_by_key(position);
+
+    Ok(ServiceOverview {
+        services: details,
+        failures,
+    })
+}
+
+// Describe a single service, failing with the reason ECS gives when it cannot
+pub(crate) async fn describe_service(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    service: &str,
+) -> Result<ServiceDetails, String> {
+    let mut overview = describe_services(profile, region, cluster, &[service.to_string()]).await?;
+    if let Some(failure) = overview.failures.first() {
+        return Err(format!(
+            "Service {} could not be described: {}",
+            service, failure.reason
+        ));
+    }
+    overview
+        .services
+        .pop()
+        .ok_or_else(|| format!("Service {} not found", service))
+}
+
+#[command]
+pub async fn ecs_describe_services(
+    profile: String,
+    region: String,
+    cluster: String,
+    services: Vec<String>,
+) -> Result<ServiceOverview, String> {
+    describe_services(&profile, &region, &cluster, &services).await
+}
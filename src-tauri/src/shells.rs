@@ -0,0 +1,390 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Manager, Window};
+
+use crate::aws::run_json_async;
+use crate::exec::{run_exec_direct, shell_quote, ExecTarget};
+use crate::storage;
+
+const SHELL_CACHE_STORE: &str = ".shell-cache.dat";
+const SHELL_CACHE_KEY: &str = "shells";
+const SHELL_MARKER: &str = "__ECS_SHELL__";
+const PROBE_TIMEOUT_SECS: u64 = 30;
+// Failed lookups and probes are retried after this long
+const FAILURE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+// Preferred shells first, paths as they would be passed to execute-command
+const LINUX_SHELLS: &[&str] = &[
+    "/bin/bash",
+    "/usr/bin/bash",
+    "/bin/ash",
+    "/bin/sh",
+    "busybox sh",
+    "/busybox/sh",
+];
+const WINDOWS_SHELLS: &[&str] = &["powershell.exe", "cmd.exe"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellDetection {
+    shell_cmd: String,
+    available: Vec<String>,
+    os_family: String,
+    image_digest: Option<String>,
+    #[serde(default)]
+    cached: bool,
+}
+
+#[derive(Clone)]
+struct ContainerPlatform {
+    os_family: String,
+    image_digest: Option<String>,
+}
+
+// Platforms of task containers, which cannot change while the task runs, and
+// recent failures to look one up or to find a shell in it, keyed by task and container
+#[derive(Default)]
+struct LookupCache {
+    platforms: HashMap<String, ContainerPlatform>,
+    failures: HashMap<String, (Instant, String)>,
+}
+
+static LOOKUPS: Mutex<Option<LookupCache>> = Mutex::new(None);
+
+fn lookup_key(target: &ExecTarget) -> String {
+    format!("{}/{}", target.task, target.container)
+}
+
+fn cached_platform(target: &ExecTarget) -> Option<ContainerPlatform> {
+    let lookups = LOOKUPS.lock().unwrap();
+    lookups.as_ref()?.platforms.get(&lookup_key(target)).cloned()
+}
+
+fn cached_failure(target: &ExecTarget) -> Option<String> {
+    let lookups = LOOKUPS.lock().unwrap();
+    let (at, error) = lookups.as_ref()?.failures.get(&lookup_key(target))?;
+    (at.elapsed() < FAILURE_CACHE_TTL).then(|| error.clone())
+}
+
+fn remember_failure(target: &ExecTarget, error: &str) {
+    let mut lookups = LOOKUPS.lock().unwrap();
+    let lookups = lookups.get_or_insert_with(LookupCache::default);
+    lookups.failures.retain(|_, (at, _)| at.elapsed() < FAILURE_CACHE_TTL);
+    lookups
+        .failures
+        .insert(lookup_key(target), (Instant::now(), error.to_string()));
+}
+
+fn is_windows(os_family: &str) -> bool {
+    os_family.to_uppercase().starts_with("WINDOWS")
+}
+
+// Work out the OS family and image digest of a task's container
+async fn container_platform(target: &ExecTarget) -> Result<ContainerPlatform, String> {
+    let v = run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-tasks",
+            "--cluster",
+            &target.cluster,
+            "--tasks",
+            &target.task,
+            "--region",
+            &target.region,
+            "--profile",
+            &target.profile,
+            "--output",
+            "json",
+        ],
+    ).await?;
+
+    let task = &v["tasks"][0];
+    if task.is_null() {
+        return Err("Task not found".to_string());
+    }
+
+    let image_digest = task["containers"]
+        .as_array()
+        .and_then(|containers| {
+            containers
+                .iter()
+                .find(|c| c["name"].as_str() == Some(target.container.as_str()))
+        })
+        .and_then(|c| c["imageDigest"].as_str())
+        .map(|s| s.to_string());
+
+    // The task definition's runtimePlatform is authoritative, Fargate tasks also report platformFamily
+    let mut os_family = None;
+    if let Some(task_definition) = task["taskDefinitionArn"].as_str() {
+        let td = run_json_async(
+            "aws",
+            &[
+                "ecs",
+                "describe-task-definition",
+                "--task-definition",
+                task_definition,
+                "--region",
+                &target.region,
+                "--profile",
+                &target.profile,
+                "--output",
+                "json",
+            ],
+        ).await?;
+        os_family = td["taskDefinition"]["runtimePlatform"]["operatingSystemFamily"]
+            .as_str()
+            .map(|s| s.to_string());
+    }
+    let os_family = os_family
+        .or_else(|| task["platformFamily"].as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "LINUX".to_string());
+
+    Ok(ContainerPlatform {
+        os_family,
+        image_digest,
+    })
+}
+
+// Shells reported by a probe, recognised by the marker lines it echoes
+fn probed_shells(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(SHELL_MARKER))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+async fn probe_linux_shells(target: &ExecTarget) -> Vec<String> {
+    let timeout = Duration::from_secs(PROBE_TIMEOUT_SECS);
+    let script = format!(
+        "for s in /bin/bash /usr/bin/bash /bin/ash /bin/sh; do [ -x \"$s\" ] && echo \"{m}$s\"; done; \
+         command -v busybox >/dev/null 2>&1 && echo \"{m}busybox sh\"",
+        m = SHELL_MARKER
+    );
+
+    // A single listing through sh covers most images
+    if let Ok(out) = run_exec_direct(target, &format!("/bin/sh -c {}", shell_quote(&script)), timeout).await {
+        let found = probed_shells(&out.output);
+        if !found.is_empty() {
+            return found;
+        }
+    }
+
+    // Images without /bin/sh get each remaining candidate tried directly
+    let mut found = Vec::new();
+    for shell in ["/bin/bash", "/busybox/sh"] {
+        let probe = format!("{} -c {}", shell, shell_quote(&format!("echo {}{}", SHELL_MARKER, shell)));
+        if let Ok(out) = run_exec_direct(target, &probe, timeout).await {
+            found.extend(probed_shells(&out.output));
+        }
+    }
+    found
+}
+
+async fn probe_windows_shells(target: &ExecTarget) -> Vec<String> {
+    let timeout = Duration::from_secs(PROBE_TIMEOUT_SECS);
+    let probes = [
+        format!("powershell.exe -NoProfile -Command \"Write-Output {}powershell.exe\"", SHELL_MARKER),
+        format!("cmd.exe /c echo {}cmd.exe", SHELL_MARKER),
+    ];
+
+    let mut found = Vec::new();
+    for probe in probes.iter() {
+        if let Ok(out) = run_exec_direct(target, probe, timeout).await {
+            found.extend(probed_shells(&out.output));
+        }
+    }
+    found
+}
+
+fn best_shell(available: &[String], windows: bool) -> Option<String> {
+    let preferred = if windows { WINDOWS_SHELLS } else { LINUX_SHELLS };
+    preferred
+        .iter()
+        .find(|shell| available.iter().any(|a| a == *shell))
+        .map(|s| s.to_string())
+}
+
+async fn detect_shell(app: &AppHandle, target: &ExecTarget, refresh: bool) -> Result<ShellDetection, String> {
+    if !refresh {
+        if let Some(error) = cached_failure(target) {
+            return Err(error);
+        }
+    }
+
+    let detection = probe_shell(app, target, refresh).await;
+    if let Err(ref e) = detection {
+        remember_failure(target, e);
+    }
+    detection
+}
+
+async fn probe_shell(app: &AppHandle, target: &ExecTarget, refresh: bool) -> Result<ShellDetection, String> {
+    let platform = match cached_platform(target) {
+        Some(platform) => platform,
+        None => {
+            let platform = container_platform(target).await?;
+            LOOKUPS
+                .lock()
+                .unwrap()
+                .get_or_insert_with(LookupCache::default)
+                .platforms
+                .insert(lookup_key(target), platform.clone());
+            platform
+        }
+    };
+
+    let mut cache: HashMap<String, ShellDetection> = storage::load(app, SHELL_CACHE_STORE, SHELL_CACHE_KEY)?;
+    if let Some(ref digest) = platform.image_digest {
+        if !refresh {
+            if let Some(hit) = cache.get(digest) {
+                let mut hit = hit.clone();
+                hit.cached = true;
+                return Ok(hit);
+            }
+        }
+    }
+
+    let windows = is_windows(&platform.os_family);
+    let available = if windows {
+        probe_windows_shells(target).await
+    } else {
+        probe_linux_shells(target).await
+    };
+
+    let shell_cmd = best_shell(&available, windows)
+        .ok_or("No usable shell found in the container")?;
+
+    let detection = ShellDetection {
+        shell_cmd,
+        available,
+        os_family: platform.os_family,
+        image_digest: platform.image_digest,
+        cached: false,
+    };
+
+    if let Some(ref digest) = detection.image_digest {
+        cache.insert(digest.clone(), detection.clone());
+        storage::save(app, SHELL_CACHE_STORE, SHELL_CACHE_KEY, &cache)?;
+    }
+
+    Ok(detection)
+}
+
+// Best shell for a container, from the cache when its image has been probed before
+pub(crate) async fn resolve_shell(app: &AppHandle, target: &ExecTarget) -> Result<String, String> {
+    detect_shell(app, target, false).await.map(|d| d.shell_cmd)
+}
+
+// An empty or "auto" shell command asks the backend to pick one
+pub(crate) fn wants_auto_shell(shell_cmd: &str) -> bool {
+    let shell_cmd = shell_cmd.trim();
+    shell_cmd.is_empty() || shell_cmd.eq_ignore_ascii_case("auto")
+}
+
+// Whether a shell command starts one of the detected shells, e.g. "/bin/bash -l"
+fn shell_available(shell_cmd: &str, available: &[String]) -> bool {
+    let shell_cmd = shell_cmd.trim();
+    available.iter().any(|shell| {
+        shell_cmd
+            .strip_prefix(shell.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+    })
+}
+
+// Whether the start of a session's output is the container runtime failing to
+// exec its shell, e.g. "exec: \"/bin/bash\": stat /bin/bash: no such file or directory"
+pub(crate) fn shell_missing(output: &str) -> bool {
+    let output = output.to_lowercase();
+    output.contains("executable file not found")
+        || (output.contains("exec failed") && output.contains("no such file or directory"))
+}
+
+// Shell to retry with after the requested one failed to start, so images without
+// bash (Alpine, distroless) still connect
+pub(crate) async fn fallback_shell(
+    app: &AppHandle,
+    target: &ExecTarget,
+    failed: &str,
+) -> Result<String, String> {
+    // A cached detection listing the failed shell is out of date
+    let mut detection = detect_shell(app, target, false).await?;
+    if detection.cached && shell_available(failed, &detection.available) {
+        detection = detect_shell(app, target, true).await?;
+    }
+    if shell_available(failed, &detection.available) {
+        return Err(format!("{} is available but did not start", failed));
+    }
+    Ok(detection.shell_cmd)
+}
+
+#[command]
+pub async fn detect_container_shell(
+    window: Window,
+    profile: String,
+    region: String,
+    cluster: String,
+    task: String,
+    container: String,
+    refresh: Option<bool>,
+) -> Result<ShellDetection, String> {
+    let target = ExecTarget {
+        profile,
+        region,
+        cluster,
+        task,
+        container,
+    };
+    detect_shell(window.app_handle(), &target, refresh.unwrap_or(false)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shells(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn best_shell_follows_preference() {
+        let available = shells(&["/bin/sh", "/bin/ash"]);
+        assert_eq!(best_shell(&available, false).as_deref(), Some("/bin/ash"));
+        assert_eq!(
+            best_shell(&shells(&["cmd.exe"]), true).as_deref(),
+            Some("cmd.exe")
+        );
+        assert_eq!(best_shell(&[], false), None);
+    }
+
+    #[test]
+    fn shell_available_matches_the_program_only() {
+        let available = shells(&["/bin/sh", "busybox sh"]);
+        assert!(shell_available("/bin/sh", &available));
+        assert!(shell_available(" /bin/sh -l", &available));
+        assert!(shell_available("busybox sh", &available));
+        assert!(!shell_available("/bin/bash", &available));
+        assert!(!shell_available("/bin/shx", &available));
+    }
+
+    #[test]
+    fn shell_missing_recognises_runtime_exec_errors() {
+        assert!(shell_missing(
+            "OCI runtime exec failed: exec failed: unable to start container process: \
+             exec: \"/bin/bash\": stat /bin/bash: no such file or directory: unknown\r\n"
+        ));
+        assert!(shell_missing("exec: \"bash\": executable file not found in $PATH"));
+        assert!(!shell_missing("Starting session with SessionId: ecs-execute-command-0abc\r\n"));
+        assert!(!shell_missing("cat: /tmp/x: No such file or directory\r\n$ "));
+    }
+
+    #[test]
+    fn auto_shell_requests() {
+        assert!(wants_auto_shell(""));
+        assert!(wants_auto_shell(" Auto "));
+        assert!(!wants_auto_shell("/bin/bash"));
+    }
+}
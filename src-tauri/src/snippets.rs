@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{command, AppHandle, Manager, Window};
+
+use crate::aws::{run_json_async, task_id_from_arn};
+use crate::exec::ExecTarget;
+use crate::{storage, terminal};
+
+const SNIPPETS_STORE: &str = ".snippets.dat";
+const SNIPPETS_KEY: &str = "snippets";
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    #[serde(default)]
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    // Command text with {{param}} placeholders
+    template: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnippetExport {
+    version: u32,
+    snippets: Vec<Snippet>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetParam {
+    name: String,
+    // Filled from the session context, None when the user has to be prompted
+    value: Option<String>,
+}
+
+fn load_snippets(app: &AppHandle) -> Result<Vec<Snippet>, String> {
+    storage::load(app, SNIPPETS_STORE, SNIPPETS_KEY)
+}
+
+fn save_snippets(app: &AppHandle, snippets: &[Snippet]) -> Result<(), String> {
+    storage::save(app, SNIPPETS_STORE, SNIPPETS_KEY, &snippets)
+}
+
+fn find_snippet(app: &AppHandle, snippet_id: &str) -> Result<Snippet, String> {
+    load_snippets(app)?
+        .into_iter()
+        .find(|s| s.id == snippet_id)
+        .ok_or_else(|| "Snippet not found".to_string())
+}
+
+// Names of the {{param}} placeholders in a template, in order of first use
+fn template_params(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let name = after[..end].trim().to_string();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &after[end + 2..];
+    }
+    names
+}
+
+fn render_template(template: &str, values: &HashMap<String, String>) -> Result<String, String> {
+    let missing: Vec<String> = template_params(template)
+        .into_iter()
+        .filter(|name| !values.contains_key(name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Missing snippet parameters: {}", missing.join(", ")));
+    }
+
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let name = after[..end].trim();
+        if name.is_empty() {
+            return Err("Snippet has an empty {{}} placeholder".to_string());
+        }
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(values.get(name).ok_or("Missing snippet parameters")?);
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+// Service name of a task, ECS records it in the task group as "service:<name>"
+async fn service_for_task(target: &ExecTarget) -> Option<String> {
+    let v = run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-tasks",
+            "--cluster",
+            &target.cluster,
+            "--tasks",
+            &target.task,
+            "--region",
+            &target.region,
+            "--profile",
+            &target.profile,
+            "--output",
+            "json",
+        ],
+    ).await.ok()?;
+
+    v["tasks"][0]["group"]
+        .as_str()
+        .and_then(|g| g.strip_prefix("service:"))
+        .map(|s| s.to_string())
+}
+
+// Parameter values known from the session the snippet will run in
+async fn context_values(session_id: &str, needed: &[String]) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let Some(target) = terminal::session_target(session_id) else {
+        return values;
+    };
+
+    values.insert("profile".to_string(), target.profile.clone());
+    values.insert("region".to_string(), target.region.clone());
+    values.insert("cluster".to_string(), target.cluster.clone());
+    values.insert("task".to_string(), target.task.clone());
+    values.insert("task_id".to_string(), task_id_from_arn(&target.task));
+    values.insert("container".to_string(), target.container.clone());
+
+    if needed.iter().any(|n| n == "service") {
+        if let Some(service) = service_for_task(&target).await {
+            values.insert("service".to_string(), service);
+        }
+    }
+
+    values
+}
+
+#[command]
+pub fn list_snippets(window: Window) -> Result<Vec<Snippet>, String> {
+    load_snippets(window.app_handle())
+}
+
+#[command]
+pub fn save_snippet(window: Window, mut snippet: Snippet) -> Result<Snippet, String> {
+    let app = window.app_handle();
+    let mut snippets = load_snippets(app)?;
+
+    if snippet.id.is_empty() {
+        snippet.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    match snippets.iter_mut().find(|s| s.id == snippet.id) {
+        Some(existing) => *existing = snippet.clone(),
+        None => snippets.push(snippet.clone()),
+    }
+
+    save_snippets(app, &snippets)?;
+    Ok(snippet)
+}
+
+#[command]
+pub fn delete_snippet(window: Window, snippet_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let mut snippets = load_snippets(app)?;
+    snippets.retain(|s| s.id != snippet_id);
+    save_snippets(app, &snippets)
+}
+
+#[command]
+pub async fn get_snippet_params(
+    window: Window,
+    snippet_id: String,
+    session_id: String,
+) -> Result<Vec<SnippetParam>, String> {
+    let snippet = find_snippet(window.app_handle(), &snippet_id)?;
+    let names = template_params(&snippet.template);
+    let values = context_values(&session_id, &names).await;
+
+    Ok(names
+        .into_iter()
+        .map(|name| {
+            let value = values.get(&name).cloned();
+            SnippetParam { name, value }
+        })
+        .collect())
+}
+
+#[command]
+pub async fn run_snippet(
+    window: Window,
+    session_id: String,
+    snippet_id: String,
+    params: Option<HashMap<String, String>>,
+    execute: Option<bool>,
+) -> Result<String, String> {
+    let snippet = find_snippet(window.app_handle(), &snippet_id)?;
+    let names = template_params(&snippet.template);
+
+    // Values entered by the user win over the session context
+    let mut values = context_values(&session_id, &names).await;
+    values.extend(params.unwrap_or_default());
+
+    let mut command = render_template(&snippet.template, &values)?;
+    if execute.unwrap_or(true) {
+        command.push('\r');
+    }
+
+    terminal::send_to_session(&session_id, command.clone().into_bytes())?;
+    Ok(command)
+}
+
+#[command]
+pub fn export_snippets(window: Window, path: String) -> Result<usize, String> {
+    let snippets = load_snippets(window.app_handle())?;
+    let export = SnippetExport {
+        version: EXPORT_VERSION,
+        snippets,
+    };
+    let content = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize snippets: {}", e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write snippets to {}: {}", path, e))?;
+    Ok(export.snippets.len())
+}
+
+#[command]
+pub fn import_snippets(window: Window, path: String, replace: Option<bool>) -> Result<usize, String> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read snippets from {}: {}", path, e))?;
+    let import: SnippetExport = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid snippet file: {}", e))?;
+    if import.version > EXPORT_VERSION {
+        return Err(format!("Unsupported snippet file version: {}", import.version));
+    }
+
+    let app = window.app_handle();
+    let mut snippets = if replace.unwrap_or(false) {
+        Vec::new()
+    } else {
+        load_snippets(app)?
+    };
+
+    let count = import.snippets.len();
+    for mut snippet in import.snippets {
+        if snippet.id.is_empty() {
+            snippet.id = uuid::Uuid::new_v4().to_string();
+        }
+        match snippets.iter_mut().find(|s| s.id == snippet.id) {
+            Some(existing) => *existing = snippet,
+            None => snippets.push(snippet),
+        }
+    }
+
+    save_snippets(app, &snippets)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn template_params_in_order_of_first_use() {
+        assert_eq!(
+            template_params("tail -n {{ lines }} {{file}} | grep {{lines}} {{}}"),
+            vec!["lines", "file"]
+        );
+    }
+
+    #[test]
+    fn render_template_fills_placeholders() {
+        let rendered = render_template(
+            "tail -n {{ lines }} {{file}}",
+            &values(&[("lines", "50"), ("file", "/var/log/app.log")]),
+        );
+        assert_eq!(rendered.unwrap(), "tail -n 50 /var/log/app.log");
+    }
+
+    #[test]
+    fn render_template_leaves_unterminated_braces() {
+        let rendered = render_template("echo {{name}} {{oops", &values(&[("name", "x")]));
+        assert_eq!(rendered.unwrap(), "echo x {{oops");
+    }
+
+    #[test]
+    fn render_template_reports_missing_parameters() {
+        let err = render_template("echo {{a}} {{b}}", &values(&[("a", "1")])).unwrap_err();
+        assert!(err.contains('b'));
+    }
+
+    #[test]
+    fn render_template_rejects_empty_placeholders() {
+        assert!(render_template("echo {{}}", &HashMap::new()).is_err());
+        assert!(render_template("echo {{ }}", &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,926 @@
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use uuid::Uuid;
+
+// Session Manager data channel client. Speaks the same websocket protocol as
+// session-manager-plugin: a JSON open message, then binary client messages that
+// carry stream data, acknowledgements and control payloads.
+
+const CLIENT_VERSION: &str = "1.2.0.0";
+
+const INPUT_STREAM: &str = "input_stream_data";
+const OUTPUT_STREAM: &str = "output_stream_data";
+const ACKNOWLEDGE: &str = "acknowledge";
+const CHANNEL_CLOSED: &str = "channel_closed";
+const START_PUBLICATION: &str = "start_publication";
+const PAUSE_PUBLICATION: &str = "pause_publication";
+
+// Binary layout: header length, message type, schema version, created date,
+// sequence number, flags, message id, payload digest, payload type, payload
+// length and payload. Integers are big-endian.
+const MESSAGE_TYPE_LEN: usize = 32;
+const SCHEMA_VERSION_OFFSET: usize = 4 + MESSAGE_TYPE_LEN;
+const CREATED_DATE_OFFSET: usize = SCHEMA_VERSION_OFFSET + 4;
+const SEQUENCE_OFFSET: usize = CREATED_DATE_OFFSET + 8;
+const FLAGS_OFFSET: usize = SEQUENCE_OFFSET + 8;
+const MESSAGE_ID_OFFSET: usize = FLAGS_OFFSET + 8;
+const DIGEST_OFFSET: usize = MESSAGE_ID_OFFSET + 16;
+const PAYLOAD_TYPE_OFFSET: usize = DIGEST_OFFSET + 32;
+// The header length field counts up to, not including, the payload length
+const HEADER_LEN: usize = PAYLOAD_TYPE_OFFSET + 4;
+
+const FLAG_DATA: u64 = 0;
+const FLAG_ACK: u64 = 3;
+
+const PAYLOAD_OUTPUT: u32 = 1;
+const PAYLOAD_SIZE: u32 = 3;
+const PAYLOAD_HANDSHAKE_REQUEST: u32 = 5;
+const PAYLOAD_HANDSHAKE_RESPONSE: u32 = 6;
+const PAYLOAD_HANDSHAKE_COMPLETE: u32 = 7;
+const PAYLOAD_ENC_CHALLENGE_REQUEST: u32 = 8;
+const PAYLOAD_FLAG: u32 = 10;
+const PAYLOAD_STDERR: u32 = 11;
+
+const ACTION_SUCCESS: u32 = 1;
+const ACTION_UNSUPPORTED: u32 = 3;
+const FLAG_TERMINATE_SESSION: u32 = 2;
+
+// Bytes buffered between the data channel and the terminal in each direction
+const IO_BUFFER: usize = 64 * 1024;
+const INPUT_CHUNK: usize = 1024;
+// Stop reading input while this many messages wait for an acknowledgement
+const MAX_UNACKED: usize = 100;
+const RESEND_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+const RESEND_AFTER: Duration = Duration::from_secs(3);
+const PING_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClientMessage {
+    pub message_type: String,
+    pub schema_version: u32,
+    pub created_date: u64,
+    pub sequence_number: i64,
+    pub flags: u64,
+    pub message_id: Uuid,
+    pub payload_type: u32,
+    pub payload: Vec<u8>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl ClientMessage {
+    pub(crate) fn new(
+        message_type: &str,
+        sequence_number: i64,
+        flags: u64,
+        payload_type: u32,
+        payload: Vec<u8>,
+    ) -> Self {
+        Self {
+            message_type: message_type.to_string(),
+            schema_version: 1,
+            created_date: now_millis(),
+            sequence_number,
+            flags,
+            message_id: Uuid::new_v4(),
+            payload_type,
+            payload,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_LEN + 4 + self.payload.len());
+        data.extend_from_slice(&(HEADER_LEN as u32).to_be_bytes());
+        let mut message_type = self.message_type.as_bytes().to_vec();
+        message_type.resize(MESSAGE_TYPE_LEN, b' ');
+        data.extend_from_slice(&message_type);
+        data.extend_from_slice(&self.schema_version.to_be_bytes());
+        data.extend_from_slice(&self.created_date.to_be_bytes());
+        data.extend_from_slice(&self.sequence_number.to_be_bytes());
+        data.extend_from_slice(&self.flags.to_be_bytes());
+        // The agent stores the least significant half of the UUID first
+        let id = self.message_id.as_bytes();
+        data.extend_from_slice(&id[8..]);
+        data.extend_from_slice(&id[..8]);
+        data.extend_from_slice(&Sha256::digest(&self.payload));
+        data.extend_from_slice(&self.payload_type.to_be_bytes());
+        data.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(&self.payload);
+        data
+    }
+
+    pub(crate) fn decode(data: &[u8]) -> Result<Self, String> {
+        if data.len() < HEADER_LEN + 4 {
+            return Err(format!("Session message too short ({} bytes)", data.len()));
+        }
+        let header_len = read_u32(data, 0) as usize;
+        if header_len < HEADER_LEN || data.len() < header_len + 4 {
+            return Err(format!(
+                "Invalid session message header length {}",
+                header_len
+            ));
+        }
+        let payload_len = read_u32(data, header_len) as usize;
+        let payload_start = header_len + 4;
+        let payload = data
+            .get(payload_start..payload_start + payload_len)
+            .ok_or("Session message payload is truncated")?
+            .to_vec();
+
+        if Sha256::digest(&payload).as_slice() != &data[DIGEST_OFFSET..DIGEST_OFFSET + 32] {
+            return Err("Session message payload digest does not match".to_string());
+        }
+
+        let mut id = [0u8; 16];
+        id[8..].copy_from_slice(&data[MESSAGE_ID_OFFSET..MESSAGE_ID_OFFSET + 8]);
+        id[..8].copy_from_slice(&data[MESSAGE_ID_OFFSET + 8..MESSAGE_ID_OFFSET + 16]);
+
+        Ok(Self {
+            message_type: String::from_utf8_lossy(&data[4..4 + MESSAGE_TYPE_LEN])
+                .trim_matches(|c: char| c == ' ' || c == '\0')
+                .to_string(),
+            schema_version: read_u32(data, SCHEMA_VERSION_OFFSET),
+            created_date: read_u64(data, CREATED_DATE_OFFSET),
+            sequence_number: read_u64(data, SEQUENCE_OFFSET) as i64,
+            flags: read_u64(data, FLAGS_OFFSET),
+            message_id: Uuid::from_bytes(id),
+            payload_type: read_u32(data, PAYLOAD_TYPE_OFFSET),
+            payload,
+        })
+    }
+
+    fn acknowledgement(&self) -> Self {
+        let payload = json!({
+            "AcknowledgedMessageType": self.message_type,
+            "AcknowledgedMessageId": self.message_id.to_string(),
+            "AcknowledgedMessageSequenceNumber": self.sequence_number,
+            "IsSequentialMessage": true,
+        });
+        Self::new(
+            ACKNOWLEDGE,
+            0,
+            FLAG_ACK,
+            0,
+            payload.to_string().into_bytes(),
+        )
+    }
+}
+
+enum Control {
+    Resize { cols: u16, rows: u16 },
+    Terminate,
+}
+
+// Handle for steering a running data channel from outside
+#[derive(Clone)]
+pub(crate) struct SessionControl(mpsc::UnboundedSender<Control>);
+
+impl SessionControl {
+    pub(crate) fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+        self.0
+            .send(Control::Resize { cols, rows })
+            .map_err(|_| "Session has ended".to_string())
+    }
+
+    pub(crate) fn terminate(&self) {
+        let _ = self.0.send(Control::Terminate);
+    }
+}
+
+pub(crate) struct NativeSession {
+    // Read for session output, write for keyboard input
+    pub terminal: DuplexStream,
+    // Stderr payloads and notices from the agent
+    pub errors: DuplexStream,
+    pub control: SessionControl,
+    // Resolves once the data channel has closed
+    pub finished: oneshot::Receiver<Result<(), String>>,
+}
+
+// What an incoming message means for the terminal
+enum Delivery {
+    Output(Vec<u8>),
+    Error(Vec<u8>),
+    Closed,
+}
+
+// Protocol bookkeeping, kept apart from the websocket so it only decides what
+// to send and deliver
+struct ChannelState {
+    next_sequence: i64,
+    expected_sequence: i64,
+    // Stream data sent but not yet acknowledged, by sequence number
+    unacked: BTreeMap<i64, (ClientMessage, Instant)>,
+    // Output that arrived ahead of a gap in the sequence
+    early: BTreeMap<i64, ClientMessage>,
+    handshake_complete: bool,
+    paused: bool,
+    size: Option<(u16, u16)>,
+    // Messages waiting to be written to the websocket
+    outbox: Vec<ClientMessage>,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            next_sequence: 0,
+            expected_sequence: 0,
+            unacked: BTreeMap::new(),
+            early: BTreeMap::new(),
+            handshake_complete: false,
+            paused: false,
+            size: None,
+            outbox: Vec::new(),
+        }
+    }
+
+    fn accepts_input(&self) -> bool {
+        self.handshake_complete && !self.paused && self.unacked.len() < MAX_UNACKED
+    }
+
+    fn send_stream(&mut self, payload_type: u32, payload: Vec<u8>) {
+        let message = ClientMessage::new(
+            INPUT_STREAM,
+            self.next_sequence,
+            FLAG_DATA,
+            payload_type,
+            payload,
+        );
+        self.next_sequence += 1;
+        self.unacked
+            .insert(message.sequence_number, (message.clone(), Instant::now()));
+        self.outbox.push(message);
+    }
+
+    fn send_size(&mut self) {
+        if let Some((cols, rows)) = self.size {
+            let payload = json!({ "cols": cols, "rows": rows });
+            self.send_stream(PAYLOAD_SIZE, payload.to_string().into_bytes());
+        }
+    }
+
+    fn resize(&mut self, cols: u16, rows: u16) {
+        self.size = Some((cols, rows));
+        if self.handshake_complete {
+            self.send_size();
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.send_stream(PAYLOAD_FLAG, FLAG_TERMINATE_SESSION.to_be_bytes().to_vec());
+    }
+
+    // Resend stream data the agent has not acknowledged in time
+    fn resend_unacked(&mut self) {
+        let now = Instant::now();
+        for (message, sent) in self.unacked.values_mut() {
+            if now.duration_since(*sent) >= RESEND_AFTER {
+                *sent = now;
+                self.outbox.push(message.clone());
+            }
+        }
+    }
+
+    fn receive(&mut self, message: ClientMessage) -> Result<Vec<Delivery>, String> {
+        match message.message_type.as_str() {
+            OUTPUT_STREAM => {
+                self.outbox.push(message.acknowledgement());
+                if message.sequence_number < self.expected_sequence {
+                    // Already delivered, the agent missed our acknowledgement
+                    return Ok(Vec::new());
+                }
+                if message.sequence_number > self.expected_sequence {
+                    self.early.insert(message.sequence_number, message);
+                    return Ok(Vec::new());
+                }
+
+                let mut deliveries = Vec::new();
+                let mut next = Some(message);
+                while let Some(message) = next {
+                    self.expected_sequence += 1;
+                    deliveries.extend(self.process_output(message)?);
+                    next = self.early.remove(&self.expected_sequence);
+                }
+                Ok(deliveries)
+            }
+            ACKNOWLEDGE => {
+                let payload: Value = serde_json::from_slice(&message.payload)
+                    .map_err(|e| format!("Invalid acknowledgement: {}", e))?;
+                if let Some(sequence) = payload["AcknowledgedMessageSequenceNumber"].as_i64() {
+                    self.unacked.remove(&sequence);
+                }
+                Ok(Vec::new())
+            }
+            CHANNEL_CLOSED => {
+                let payload: Value = serde_json::from_slice(&message.payload).unwrap_or_default();
+                let mut deliveries = Vec::new();
+                if let Some(output) = payload["Output"].as_str().filter(|s| !s.is_empty()) {
+                    deliveries.push(Delivery::Error(format!("{}\r\n", output).into_bytes()));
+                }
+                deliveries.push(Delivery::Closed);
+                Ok(deliveries)
+            }
+            PAUSE_PUBLICATION => {
+                self.paused = true;
+                Ok(Vec::new())
+            }
+            START_PUBLICATION => {
+                self.paused = false;
+                Ok(Vec::new())
+            }
+            other => {
+                eprintln!("[DEBUG] Ignoring session message type {}", other);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn process_output(&mut self, message: ClientMessage) -> Result<Vec<Delivery>, String> {
+        match message.payload_type {
+            PAYLOAD_OUTPUT => Ok(vec![Delivery::Output(message.payload)]),
+            PAYLOAD_STDERR => Ok(vec![Delivery::Error(message.payload)]),
+            PAYLOAD_HANDSHAKE_REQUEST => self.handshake(&message.payload).map(|_| Vec::new()),
+            PAYLOAD_HANDSHAKE_COMPLETE => {
+                self.handshake_complete = true;
+                self.send_size();
+                let payload: Value = serde_json::from_slice(&message.payload).unwrap_or_default();
+                Ok(payload["CustomerMessage"]
+                    .as_str()
+                    .filter(|s| !s.is_empty())
+                    .map(|s| Delivery::Error(format!("{}\r\n", s).into_bytes()))
+                    .into_iter()
+                    .collect())
+            }
+            PAYLOAD_ENC_CHALLENGE_REQUEST => {
+                Err("Session requested an encryption challenge, which is not supported".to_string())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    // Answer the agent's requested client actions. Only plain sessions are
+    // supported, KMS-encrypted sessions need session-manager-plugin.
+    fn handshake(&mut self, payload: &[u8]) -> Result<(), String> {
+        let request: Value = serde_json::from_slice(payload)
+            .map_err(|e| format!("Invalid handshake request: {}", e))?;
+
+        let mut processed = Vec::new();
+        let mut errors = Vec::new();
+        for action in request["RequestedClientActions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            let action_type = action["ActionType"].as_str().unwrap_or_default();
+            if action_type == "SessionType" {
+                processed
+                    .push(json!({ "ActionType": action_type, "ActionStatus": ACTION_SUCCESS }));
+            } else {
+                let error = format!(
+                    "{} is not supported by the native session client",
+                    action_type
+                );
+                processed.push(json!({
+                    "ActionType": action_type,
+                    "ActionStatus": ACTION_UNSUPPORTED,
+                    "Error": error,
+                }));
+                errors.push(error);
+            }
+        }
+
+        let response = json!({
+            "ClientVersion": CLIENT_VERSION,
+            "ProcessedClientActions": processed,
+            "Errors": errors,
+        });
+        self.send_stream(
+            PAYLOAD_HANDSHAKE_RESPONSE,
+            response.to_string().into_bytes(),
+        );
+
+        match errors.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn ws_error(e: WsError) -> String {
+    format!("Session websocket error: {}", e)
+}
+
+async fn flush<S>(ws: &mut S, state: &mut ChannelState) -> Result<(), String>
+where
+    S: Sink<Message, Error = WsError> + Unpin,
+{
+    for message in state.outbox.drain(..) {
+        ws.feed(Message::binary(message.encode()))
+            .await
+            .map_err(ws_error)?;
+    }
+    ws.flush().await.map_err(ws_error)
+}
+
+async fn run_channel<S>(
+    mut ws: S,
+    token: String,
+    mut io: DuplexStream,
+    mut errors: DuplexStream,
+    mut control: mpsc::UnboundedReceiver<Control>,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<Message, WsError>> + Sink<Message, Error = WsError> + Unpin,
+{
+    let open = json!({
+        "MessageSchemaVersion": "1.0",
+        "RequestId": Uuid::new_v4().to_string(),
+        "TokenValue": token,
+        "ClientId": Uuid::new_v4().to_string(),
+        "ClientVersion": CLIENT_VERSION,
+    });
+    ws.send(Message::text(open.to_string()))
+        .await
+        .map_err(ws_error)?;
+
+    let mut state = ChannelState::new();
+    let mut input = [0u8; INPUT_CHUNK];
+    let mut resend = tokio::time::interval(RESEND_CHECK_INTERVAL);
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+
+    loop {
+        let accepts_input = state.accepts_input();
+        tokio::select! {
+            frame = ws.next() => match frame {
+                Some(Ok(Message::Binary(data))) => {
+                    let message = ClientMessage::decode(&data)?;
+                    let deliveries = match state.receive(message) {
+                        Ok(deliveries) => deliveries,
+                        Err(e) => {
+                            // Let the agent see our handshake response before giving up
+                            let _ = flush(&mut ws, &mut state).await;
+                            return Err(e);
+                        }
+                    };
+                    for delivery in deliveries {
+                        match delivery {
+                            Delivery::Output(data) => {
+                                io.write_all(&data).await.map_err(|e| e.to_string())?
+                            }
+                            Delivery::Error(data) => {
+                                let _ = errors.write_all(&data).await;
+                            }
+                            Delivery::Closed => {
+                                let _ = flush(&mut ws, &mut state).await;
+                                let _ = ws.close().await;
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Text(text))) => {
+                    let _ = errors.write_all(format!("{}\r\n", text.as_str()).as_bytes()).await;
+                }
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(ws_error(e)),
+            },
+            read = io.read(&mut input), if accepts_input => match read {
+                Ok(n) if n > 0 => state.send_stream(PAYLOAD_OUTPUT, input[..n].to_vec()),
+                // The terminal side went away, end the session
+                _ => {
+                    state.terminate();
+                    let _ = flush(&mut ws, &mut state).await;
+                    let _ = ws.close().await;
+                    return Ok(());
+                }
+            },
+            command = control.recv() => match command {
+                Some(Control::Resize { cols, rows }) => state.resize(cols, rows),
+                Some(Control::Terminate) | None => {
+                    state.terminate();
+                    let _ = flush(&mut ws, &mut state).await;
+                    let _ = ws.close().await;
+                    return Ok(());
+                }
+            },
+            _ = resend.tick() => state.resend_unacked(),
+            _ = ping.tick() => {
+                ws.send(Message::Ping(Default::default())).await.map_err(ws_error)?;
+            }
+        }
+        flush(&mut ws, &mut state).await?;
+    }
+}
+
+// Run a data channel over an already open websocket. Generic over the stream so
+// it can be driven by a local stand-in as well as the real service.
+pub(crate) fn start<S>(ws: S, token: String) -> NativeSession
+where
+    S: Stream<Item = Result<Message, WsError>>
+        + Sink<Message, Error = WsError>
+        + Unpin
+        + Send
+        + 'static,
+{
+    let (terminal, io) = tokio::io::duplex(IO_BUFFER);
+    let (errors, errors_writer) = tokio::io::duplex(IO_BUFFER);
+    let (control_tx, control_rx) = mpsc::unbounded_channel();
+    let (finished_tx, finished) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let result = run_channel(ws, token, io, errors_writer, control_rx).await;
+        if let Err(ref e) = result {
+            eprintln!("[DEBUG] Session data channel failed: {}", e);
+        }
+        let _ = finished_tx.send(result);
+    });
+
+    NativeSession {
+        terminal,
+        errors,
+        control: SessionControl(control_tx),
+        finished,
+    }
+}
+
+// Open the stream URL returned by ExecuteCommand and start the data channel
+pub(crate) async fn connect(stream_url: &str, token: &str) -> Result<NativeSession, String> {
+    let (ws, _) = tokio_tungstenite::connect_async(stream_url)
+        .await
+        .map_err(|e| format!("Failed to open session stream: {}", e))?;
+    Ok(start(ws, token.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::WebSocketStream;
+
+    type Agent = WebSocketStream<DuplexStream>;
+
+    // A session over an in-memory websocket, with the far end standing in for the agent
+    async fn session() -> (NativeSession, Agent) {
+        let (client, server) = tokio::io::duplex(IO_BUFFER);
+        let (client, agent) = tokio::join!(
+            WebSocketStream::from_raw_socket(client, Role::Client, None),
+            WebSocketStream::from_raw_socket(server, Role::Server, None),
+        );
+        let mut agent = agent;
+        let session = start(client, "token".to_string());
+
+        let open = match agent.next().await {
+            Some(Ok(Message::Text(text))) => serde_json::from_str::<Value>(text.as_str()).unwrap(),
+            other => panic!("expected the open message, got {:?}", other),
+        };
+        assert_eq!(open["TokenValue"], "token");
+        assert_eq!(open["ClientVersion"], CLIENT_VERSION);
+        (session, agent)
+    }
+
+    async fn send(agent: &mut Agent, sequence: i64, payload_type: u32, payload: &[u8]) {
+        let message = ClientMessage::new(
+            OUTPUT_STREAM,
+            sequence,
+            FLAG_DATA,
+            payload_type,
+            payload.to_vec(),
+        );
+        agent.send(Message::binary(message.encode())).await.unwrap();
+    }
+
+    async fn recv(agent: &mut Agent) -> ClientMessage {
+        loop {
+            match agent.next().await {
+                Some(Ok(Message::Binary(data))) => return ClientMessage::decode(&data).unwrap(),
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                other => panic!("expected a binary message, got {:?}", other),
+            }
+        }
+    }
+
+    // Next stream message from the client, acknowledging it like the agent does
+    async fn recv_stream(agent: &mut Agent) -> ClientMessage {
+        loop {
+            let message = recv(agent).await;
+            if message.message_type == ACKNOWLEDGE {
+                continue;
+            }
+            // The client may already be gone after sending its last message
+            let ack = message.acknowledgement();
+            let _ = agent.send(Message::binary(ack.encode())).await;
+            return message;
+        }
+    }
+
+    async fn recv_ack(agent: &mut Agent) -> i64 {
+        let message = recv(agent).await;
+        assert_eq!(message.message_type, ACKNOWLEDGE);
+        assert_eq!(message.flags, FLAG_ACK);
+        let payload: Value = serde_json::from_slice(&message.payload).unwrap();
+        payload["AcknowledgedMessageSequenceNumber"]
+            .as_i64()
+            .unwrap()
+    }
+
+    async fn handshake(agent: &mut Agent) {
+        let request = json!({
+            "AgentVersion": "3.3.0.0",
+            "RequestedClientActions": [
+                { "ActionType": "SessionType", "ActionParameters": { "SessionType": "Standard_Stream" } }
+            ],
+        });
+        send(
+            agent,
+            0,
+            PAYLOAD_HANDSHAKE_REQUEST,
+            request.to_string().as_bytes(),
+        )
+        .await;
+        assert_eq!(recv_ack(agent).await, 0);
+
+        let response = recv_stream(agent).await;
+        assert_eq!(response.message_type, INPUT_STREAM);
+        assert_eq!(response.payload_type, PAYLOAD_HANDSHAKE_RESPONSE);
+        let response: Value = serde_json::from_slice(&response.payload).unwrap();
+        assert_eq!(
+            response["ProcessedClientActions"][0]["ActionStatus"],
+            ACTION_SUCCESS
+        );
+        assert_eq!(response["Errors"], json!([]));
+
+        send(agent, 1, PAYLOAD_HANDSHAKE_COMPLETE, b"{}").await;
+        assert_eq!(recv_ack(agent).await, 1);
+    }
+
+    async fn read_exact(reader: &mut DuplexStream, len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        reader.read_exact(&mut data).await.unwrap();
+        data
+    }
+
+    #[test]
+    fn message_round_trips() {
+        let message = ClientMessage::new(
+            OUTPUT_STREAM,
+            42,
+            FLAG_DATA,
+            PAYLOAD_OUTPUT,
+            b"hello".to_vec(),
+        );
+        let data = message.encode();
+        assert_eq!(read_u32(&data, 0) as usize, HEADER_LEN);
+        assert_eq!(
+            &data[DIGEST_OFFSET..DIGEST_OFFSET + 32],
+            Sha256::digest(b"hello").as_slice()
+        );
+        assert_eq!(ClientMessage::decode(&data).unwrap(), message);
+    }
+
+    #[test]
+    fn message_type_is_padded_and_trimmed() {
+        let message = ClientMessage::new(ACKNOWLEDGE, 0, FLAG_ACK, 0, Vec::new());
+        let data = message.encode();
+        assert_eq!(
+            &data[4..4 + MESSAGE_TYPE_LEN],
+            format!("{:<32}", ACKNOWLEDGE).as_bytes()
+        );
+        assert_eq!(
+            ClientMessage::decode(&data).unwrap().message_type,
+            ACKNOWLEDGE
+        );
+    }
+
+    #[test]
+    fn decode_rejects_bad_digest_and_short_messages() {
+        let mut data = ClientMessage::new(
+            OUTPUT_STREAM,
+            0,
+            FLAG_DATA,
+            PAYLOAD_OUTPUT,
+            b"hello".to_vec(),
+        )
+        .encode();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        assert!(ClientMessage::decode(&data).unwrap_err().contains("digest"));
+        assert!(ClientMessage::decode(&data[..HEADER_LEN]).is_err());
+        assert!(ClientMessage::decode(&data[..last])
+            .unwrap_err()
+            .contains("truncated"));
+    }
+
+    #[test]
+    fn acknowledgement_clears_unacked_input() {
+        let mut state = ChannelState::new();
+        state.send_stream(PAYLOAD_OUTPUT, b"ls\r".to_vec());
+        state.send_stream(PAYLOAD_OUTPUT, b"pwd\r".to_vec());
+        let sent = state.outbox.drain(..).collect::<Vec<_>>();
+        assert_eq!(sent[0].sequence_number, 0);
+        assert_eq!(sent[1].sequence_number, 1);
+        assert_eq!(state.unacked.len(), 2);
+
+        state.receive(sent[0].acknowledgement()).unwrap();
+        assert_eq!(state.unacked.keys().copied().collect::<Vec<_>>(), vec![1]);
+        assert!(state.outbox.is_empty());
+    }
+
+    #[test]
+    fn unacked_input_is_resent() {
+        let mut state = ChannelState::new();
+        state.send_stream(PAYLOAD_OUTPUT, b"ls\r".to_vec());
+        state.outbox.clear();
+
+        state.resend_unacked();
+        assert!(state.outbox.is_empty());
+
+        for (_, sent) in state.unacked.values_mut() {
+            *sent -= RESEND_AFTER;
+        }
+        state.resend_unacked();
+        assert_eq!(state.outbox.len(), 1);
+        assert_eq!(state.outbox[0].payload, b"ls\r");
+    }
+
+    #[test]
+    fn input_waits_for_handshake_and_publication() {
+        let mut state = ChannelState::new();
+        assert!(!state.accepts_input());
+        state.handshake_complete = true;
+        assert!(state.accepts_input());
+
+        state
+            .receive(ClientMessage::new(
+                PAUSE_PUBLICATION,
+                0,
+                FLAG_DATA,
+                0,
+                Vec::new(),
+            ))
+            .unwrap();
+        assert!(!state.accepts_input());
+        state
+            .receive(ClientMessage::new(
+                START_PUBLICATION,
+                0,
+                FLAG_DATA,
+                0,
+                Vec::new(),
+            ))
+            .unwrap();
+        assert!(state.accepts_input());
+    }
+
+    #[tokio::test]
+    async fn handshake_then_input_and_resize() {
+        let (mut session, mut agent) = session().await;
+        session.control.resize(120, 40).unwrap();
+        handshake(&mut agent).await;
+
+        // The size requested before the handshake goes out once it completes
+        let size = recv_stream(&mut agent).await;
+        assert_eq!(size.payload_type, PAYLOAD_SIZE);
+        let size: Value = serde_json::from_slice(&size.payload).unwrap();
+        assert_eq!(size, json!({ "cols": 120, "rows": 40 }));
+
+        session.terminal.write_all(b"ls\r").await.unwrap();
+        let input = recv_stream(&mut agent).await;
+        assert_eq!(input.message_type, INPUT_STREAM);
+        assert_eq!(input.payload_type, PAYLOAD_OUTPUT);
+        assert_eq!(input.payload, b"ls\r");
+        // Handshake response and size came first
+        assert_eq!(input.sequence_number, 2);
+
+        send(&mut agent, 2, PAYLOAD_OUTPUT, b"file\r\n").await;
+        assert_eq!(recv_ack(&mut agent).await, 2);
+        assert_eq!(read_exact(&mut session.terminal, 6).await, b"file\r\n");
+    }
+
+    #[tokio::test]
+    async fn output_is_reordered_and_deduplicated() {
+        let (mut session, mut agent) = session().await;
+        handshake(&mut agent).await;
+
+        send(&mut agent, 3, PAYLOAD_OUTPUT, b"b").await;
+        assert_eq!(recv_ack(&mut agent).await, 3);
+        send(&mut agent, 2, PAYLOAD_OUTPUT, b"a").await;
+        assert_eq!(recv_ack(&mut agent).await, 2);
+        // A resend of something already delivered is acknowledged again but not repeated
+        send(&mut agent, 2, PAYLOAD_OUTPUT, b"a").await;
+        assert_eq!(recv_ack(&mut agent).await, 2);
+        send(&mut agent, 4, PAYLOAD_OUTPUT, b"c").await;
+        assert_eq!(recv_ack(&mut agent).await, 4);
+
+        assert_eq!(read_exact(&mut session.terminal, 3).await, b"abc");
+    }
+
+    #[tokio::test]
+    async fn stderr_goes_to_the_error_stream() {
+        let (mut session, mut agent) = session().await;
+        handshake(&mut agent).await;
+
+        send(&mut agent, 2, PAYLOAD_STDERR, b"oops").await;
+        assert_eq!(recv_ack(&mut agent).await, 2);
+        assert_eq!(read_exact(&mut session.errors, 4).await, b"oops");
+    }
+
+    #[tokio::test]
+    async fn unsupported_handshake_action_fails_the_session() {
+        let (session, mut agent) = session().await;
+        let request = json!({
+            "RequestedClientActions": [
+                { "ActionType": "KMSEncryption", "ActionParameters": { "KMSKeyId": "key" } }
+            ],
+        });
+        send(
+            &mut agent,
+            0,
+            PAYLOAD_HANDSHAKE_REQUEST,
+            request.to_string().as_bytes(),
+        )
+        .await;
+        assert_eq!(recv_ack(&mut agent).await, 0);
+
+        let response = recv(&mut agent).await;
+        assert_eq!(response.payload_type, PAYLOAD_HANDSHAKE_RESPONSE);
+        let response: Value = serde_json::from_slice(&response.payload).unwrap();
+        assert_eq!(
+            response["ProcessedClientActions"][0]["ActionStatus"],
+            ACTION_UNSUPPORTED
+        );
+
+        let result = session.finished.await.unwrap();
+        assert!(result.unwrap_err().contains("KMSEncryption"));
+    }
+
+    #[tokio::test]
+    async fn channel_closed_ends_the_session() {
+        let (mut session, mut agent) = session().await;
+        handshake(&mut agent).await;
+
+        let closed = ClientMessage::new(
+            CHANNEL_CLOSED,
+            0,
+            FLAG_DATA,
+            0,
+            json!({ "Output": "Exiting session" })
+                .to_string()
+                .into_bytes(),
+        );
+        agent.send(Message::binary(closed.encode())).await.unwrap();
+
+        assert_eq!(session.finished.await.unwrap(), Ok(()));
+        let mut errors = String::new();
+        session.errors.read_to_string(&mut errors).await.unwrap();
+        assert_eq!(errors, "Exiting session\r\n");
+    }
+
+    #[tokio::test]
+    async fn terminate_sends_the_terminate_flag() {
+        let (session, mut agent) = session().await;
+        handshake(&mut agent).await;
+
+        session.control.terminate();
+        let flag = recv_stream(&mut agent).await;
+        assert_eq!(flag.payload_type, PAYLOAD_FLAG);
+        assert_eq!(flag.payload, FLAG_TERMINATE_SESSION.to_be_bytes());
+        assert!(matches!(
+            agent.next().await,
+            Some(Ok(Message::Close(_))) | None
+        ));
+        assert_eq!(session.finished.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn closing_the_terminal_terminates_the_session() {
+        let (session, mut agent) = session().await;
+        handshake(&mut agent).await;
+
+        drop(session.terminal);
+        let flag = recv_stream(&mut agent).await;
+        assert_eq!(flag.payload_type, PAYLOAD_FLAG);
+        assert_eq!(session.finished.await.unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,38 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+// Read a value persisted by the backend, falling back to its default when missing
+pub(crate) fn load<T: DeserializeOwned + Default>(
+    app: &AppHandle,
+    file: &str,
+    key: &str,
+) -> Result<T, String> {
+    let store = app
+        .store(file)
+        .map_err(|e| format!("Failed to open store {}: {}", file, e))?;
+    match store.get(key) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Failed to read {} from {}: {}", key, file, e)),
+        None => Ok(T::default()),
+    }
+}
+
+// Persist a value and flush the store to disk
+pub(crate) fn save<T: Serialize>(
+    app: &AppHandle,
+    file: &str,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let store = app
+        .store(file)
+        .map_err(|e| format!("Failed to open store {}: {}", file, e))?;
+    let value = serde_json::to_value(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", key, e))?;
+    store.set(key, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save store {}: {}", file, e))
+}
@@ -0,0 +1,665 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use tauri::command;
+
+use crate::ecs_api;
+use crate::services::describe_service;
+
+// ListServiceDeployments pages and DescribeServiceRevisions calls take at most 20
+const HISTORY_BATCH: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDefinitionDetails {
+    pub arn: String,
+    pub family: String,
+    pub revision: i32,
+    // ACTIVE, INACTIVE or DELETE_IN_PROGRESS
+    pub status: Option<String>,
+    // Task level sizes as registered, e.g. "512" or "1 vCPU"
+    pub cpu: Option<String>,
+    pub memory: Option<String>,
+    pub network_mode: Option<String>,
+    pub requires_compatibilities: Vec<String>,
+    pub task_role_arn: Option<String>,
+    pub execution_role_arn: Option<String>,
+    pub registered_at: Option<i64>,
+    pub registered_by: Option<String>,
+    pub containers: Vec<ContainerSpec>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerSpec {
+    pub name: String,
+    pub image: String,
+    pub essential: bool,
+    pub cpu: i32,
+    pub memory: Option<i32>,
+    pub memory_reservation: Option<i32>,
+    pub entry_point: Vec<String>,
+    pub command: Vec<String>,
+    pub environment: Vec<EnvironmentVariable>,
+    pub environment_files: Vec<EnvironmentFile>,
+    pub secrets: Vec<SecretReference>,
+    pub port_mappings: Vec<PortMapping>,
+    pub log_driver: Option<String>,
+    pub health_check: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentVariable {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentFile {
+    // Only s3 is supported by ECS today
+    pub kind: String,
+    pub value: String,
+}
+
+// A secret injected from Secrets Manager or SSM Parameter Store. Only the
+// reference is part of the task definition, never the value.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretReference {
+    pub name: String,
+    pub value_from: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortMapping {
+    pub container_port: Option<i32>,
+    pub host_port: Option<i32>,
+    pub protocol: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    // Dotted path, containers and variables are addressed by name,
+    // e.g. containers.web.environment.LOG_LEVEL
+    pub path: String,
+    pub kind: ChangeKind,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDefinitionDiff {
+    pub from: TaskDefinitionDetails,
+    pub to: TaskDefinitionDetails,
+    pub changes: Vec<FieldChange>,
+}
+
+fn container_spec(c: &aws_sdk_ecs::types::ContainerDefinition) -> ContainerSpec {
+    let mut environment: Vec<EnvironmentVariable> = c
+        .environment()
+        .iter()
+        .filter_map(|kv| {
+            Some(EnvironmentVariable {
+                name: kv.name()?.to_string(),
+                value: kv.value().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+    environment.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut secrets: Vec<SecretReference> = c
+        .secrets()
+        .iter()
+        .map(|s| SecretReference {
+            name: s.name().to_string(),
+            value_from: s.value_from().to_string(),
+        })
+        .collect();
+    secrets.sort_by(|a, b| a.name.cmp(&b.name));
+
+    ContainerSpec {
+        name: c.name().unwrap_or_default().to_string(),
+        image: c.image().unwrap_or_default().to_string(),
+        // Containers are essential unless marked otherwise
+        essential: c.essential().unwrap_or(true),
+        cpu: c.cpu(),
+        memory: c.memory(),
+        memory_reservation: c.memory_reservation(),
+        entry_point: c.entry_point().to_vec(),
+        command: c.command().to_vec(),
+        environment,
+        environment_files: c
+            .environment_files()
+            .iter()
+            .map(|f| EnvironmentFile {
+                kind: f.r#type().as_str().to_string(),
+                value: f.value().to_string(),
+            })
+            .collect(),
+        secrets,
+        port_mappings: c
+            .port_mappings()
+            .iter()
+            .map(|p| PortMapping {
+                container_port: p.container_port(),
+                host_port: p.host_port(),
+                protocol: p.protocol().map(|p| p.as_str().to_string()),
+                name: p.name().map(|s| s.to_string()),
+            })
+            .collect(),
+        log_driver: c
+            .log_configuration()
+            .map(|l| l.log_driver().as_str().to_string()),
+        health_check: c.health_check().map(|h| h.command().to_vec()),
+    }
+}
+
+// Fetch a task definition by family, family:revision or ARN
+pub(crate) async fn describe_task_definition(
+    profile: &str,
+    region: &str,
+    task_definition: &str,
+) -> Result<TaskDefinitionDetails, String> {
+    let out = ecs_api::client(profile, region)
+        .await
+        .describe_task_definition()
+        .task_definition(task_definition)
+        .send()
+        .await
+        .map_err(|e| format!("DescribeTaskDefinition failed: {}", DisplayErrorContext(e)))?;
+    let td = out
+        .task_definition()
+        .ok_or_else(|| format!("Task definition {} not found", task_definition))?;
+
+    Ok(TaskDefinitionDetails {
+        arn: td.task_definition_arn().unwrap_or_default().to_string(),
+        family: td.family().unwrap_or_default().to_string(),
+        revision: td.revision(),
+        status: td.status().map(|s| s.as_str().to_string()),
+        cpu: td.cpu().map(|s| s.to_string()),
+        memory: td.memory().map(|s| s.to_string()),
+        network_mode: td.network_mode().map(|m| m.as_str().to_string()),
+        requires_compatibilities: td
+            .requires_compatibilities()
+            .iter()
+            .map(|c| c.as_str().to_string())
+            .collect(),
+        task_role_arn: td.task_role_arn().map(|s| s.to_string()),
+        execution_role_arn: td.execution_role_arn().map(|s| s.to_string()),
+        registered_at: td.registered_at().and_then(|t| t.to_millis().ok()),
+        registered_by: td.registered_by().map(|s| s.to_string()),
+        containers: td
+            .container_definitions()
+            .iter()
+            .map(container_spec)
+            .collect(),
+    })
+}
+
+// Docker style "hostPort:containerPort/protocol", one container port can be
+// published on several host ports
+fn port_mapping_key(p: &PortMapping) -> String {
+    let container_port = p.container_port.unwrap_or_default();
+    let protocol = p.protocol.as_deref().unwrap_or("tcp");
+    match p.host_port {
+        Some(host_port) => format!("{}:{}/{}", host_port, container_port, protocol),
+        None => format!("{}/{}", container_port, protocol),
+    }
+}
+
+// The parts of a task definition worth comparing between revisions. Lists
+// are keyed by name so a diff reports a changed variable rather than a
+// shifted array.
+fn comparable(td: &TaskDefinitionDetails) -> Value {
+    let containers: Map<String, Value> = td
+        .containers
+        .iter()
+        .map(|c| {
+            let environment: Map<String, Value> = c
+                .environment
+                .iter()
+                .map(|e| (e.name.clone(), json!(e.value)))
+                .collect();
+            let secrets: Map<String, Value> = c
+                .secrets
+                .iter()
+                .map(|s| (s.name.clone(), json!(s.value_from)))
+                .collect();
+            let port_mappings: Map<String, Value> = c
+                .port_mappings
+                .iter()
+                .map(|p| (port_mapping_key(p), json!({ "name": p.name })))
+                .collect();
+            let value = json!({
+                "image": c.image,
+                "essential": c.essential,
+                "cpu": c.cpu,
+                "memory": c.memory,
+                "memoryReservation": c.memory_reservation,
+                "entryPoint": c.entry_point,
+                "command": c.command,
+                "environment": environment,
+                "environmentFiles": c.environment_files,
+                "secrets": secrets,
+                "portMappings": port_mappings,
+                "logDriver": c.log_driver,
+                "healthCheck": c.health_check,
+            });
+            (c.name.clone(), value)
+        })
+        .collect();
+
+    json!({
+        "cpu": td.cpu,
+        "memory": td.memory,
+        "networkMode": td.network_mode,
+        "requiresCompatibilities": td.requires_compatibilities,
+        "taskRoleArn": td.task_role_arn,
+        "executionRoleArn": td.execution_role_arn,
+        "containers": containers,
+    })
+}
+
+fn diff_values(path: &str, before: &Value, after: &Value, changes: &mut Vec<FieldChange>) {
+    // Keys that would make a dotted path ambiguous, such as variable names
+    // with dots, are quoted in brackets: environment["spring.profiles"]
+    let child = |key: &str| {
+        if key.is_empty() || key.contains(['.', '[', ']', '"']) {
+            format!("{}[{}]", path, Value::String(key.to_string()))
+        } else if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                match b.get(key) {
+                    Some(other) => diff_values(&child(key), value, other, changes),
+                    None if !value.is_null() => changes.push(FieldChange {
+                        path: child(key),
+                        kind: ChangeKind::Removed,
+                        before: Some(value.clone()),
+                        after: None,
+                    }),
+                    None => {}
+                }
+            }
+            for (key, value) in b {
+                if !a.contains_key(key) && !value.is_null() {
+                    changes.push(FieldChange {
+                        path: child(key),
+                        kind: ChangeKind::Added,
+                        before: None,
+                        after: Some(value.clone()),
+                    });
+                }
+            }
+        }
+        _ if before == after => {}
+        (Value::Null, _) => changes.push(FieldChange {
+            path: path.to_string(),
+            kind: ChangeKind::Added,
+            before: None,
+            after: Some(after.clone()),
+        }),
+        (_, Value::Null) => changes.push(FieldChange {
+            path: path.to_string(),
+            kind: ChangeKind::Removed,
+            before: Some(before.clone()),
+            after: None,
+        }),
+        _ => changes.push(FieldChange {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+    }
+}
+
+fn diff(from: TaskDefinitionDetails, to: TaskDefinitionDetails) -> TaskDefinitionDiff {
+    let mut changes = Vec::new();
+    diff_values("", &comparable(&from), &comparable(&to), &mut changes);
+    TaskDefinitionDiff { from, to, changes }
+}
+
+// The task definition the service ran before `current`, from its deployment
+// history: the source of the newest deployment that changed the task
+// definition. Redeploys of the same revision are skipped.
+async fn previous_task_definition(
+    profile: &str,
+    region: &str,
+    cluster: &str,
+    service: &str,
+    current: &str,
+) -> Result<String, String> {
+    let client = ecs_api::client(profile, region).await;
+    let out = client
+        .list_service_deployments()
+        .cluster(cluster)
+        .service(service)
+        .max_results(HISTORY_BATCH as i32)
+        .send()
+        .await
+        .map_err(|e| format!("ListServiceDeployments failed: {}", DisplayErrorContext(e)))?;
+    let mut briefs = out.service_deployments().to_vec();
+    briefs.sort_by_key(|b| std::cmp::Reverse(b.created_at().and_then(|t| t.to_millis().ok())));
+    let arns: Vec<String> = briefs
+        .iter()
+        .filter_map(|b| b.service_deployment_arn().map(|s| s.to_string()))
+        .collect();
+    if arns.is_empty() {
+        return Err(format!("{} has no deployment history", service));
+    }
+
+    let out = client
+        .describe_service_deployments()
+        .set_service_deployment_arns(Some(arns.clone()))
+        .send()
+        .await
+        .map_err(|e| {
+            format!(
+                "DescribeServiceDeployments failed: {}",
+                DisplayErrorContext(e)
+            )
+        })?;
+    let deployments: HashMap<&str, &aws_sdk_ecs::types::ServiceDeployment> = out
+        .service_deployments()
+        .iter()
+        .filter_map(|d| Some((d.service_deployment_arn()?, d)))
+        .collect();
+
+    // Source revisions of each deployment, newest deployment first
+    let sources: Vec<Vec<String>> = arns
+        .iter()
+        .filter_map(|arn| deployments.get(arn.as_str()))
+        .map(|d| {
+            d.source_service_revisions()
+                .iter()
+                .filter_map(|r| r.arn().map(|s| s.to_string()))
+                .collect()
+        })
+        .collect();
+    let mut revision_arns: Vec<String> = sources.iter().flatten().cloned().collect();
+    revision_arns.sort();
+    revision_arns.dedup();
+
+    let mut task_definitions = HashMap::new();
+    for batch in revision_arns.chunks(HISTORY_BATCH) {
+        let out = client
+            .describe_service_revisions()
+            .set_service_revision_arns(Some(batch.to_vec()))
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "DescribeServiceRevisions failed: {}",
+                    DisplayErrorContext(e)
+                )
+            })?;
+        for revision in out.service_revisions() {
+            if let (Some(arn), Some(td)) =
+                (revision.service_revision_arn(), revision.task_definition())
+            {
+                task_definitions.insert(arn.to_string(), td.to_string());
+            }
+        }
+    }
+
+    sources
+        .iter()
+        .flatten()
+        .filter_map(|arn| task_definitions.get(arn))
+        .find(|td| td.as_str() != current)
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "No earlier deployment of {} with a different task definition in its deployment history",
+                service
+            )
+        })
+}
+
+#[command]
+pub async fn get_task_definition(
+    profile: String,
+    region: String,
+    task_definition: String,
+) -> Result<TaskDefinitionDetails, String> {
+    describe_task_definition(&profile, &region, &task_definition).await
+}
+
+#[command]
+pub async fn diff_task_definitions(
+    profile: String,
+    region: String,
+    from: String,
+    to: String,
+) -> Result<TaskDefinitionDiff, String> {
+    let (from, to) = tokio::join!(
+        describe_task_definition(&profile, &region, &from),
+        describe_task_definition(&profile, &region, &to)
+    );
+    Ok(diff(from?, to?))
+}
+
+// Compare what a service runs now with what it ran before. During a rollout
+// that is the deployment being replaced, otherwise the previous task
+// definition found in the service's deployment history.
+#[command]
+pub async fn diff_service_deployments(
+    profile: String,
+    region: String,
+    cluster: String,
+    service: String,
+) -> Result<TaskDefinitionDiff, String> {
+    let details = describe_service(&profile, &region, &cluster, &service).await?;
+
+    let current = details
+        .deployments
+        .iter()
+        .find(|d| d.status.as_deref() == Some("PRIMARY"))
+        .and_then(|d| d.task_definition.clone())
+        .or(details.task_definition.clone())
+        .ok_or("Service has no task definition")?;
+    let replaced = details
+        .deployments
+        .iter()
+        .filter(|d| d.status.as_deref() == Some("ACTIVE"))
+        .max_by_key(|d| d.created_at)
+        .and_then(|d| d.task_definition.clone());
+
+    let previous = match replaced {
+        Some(previous) => previous,
+        None => previous_task_definition(&profile, &region, &cluster, &service, &current).await?,
+    };
+    let (from, to) = tokio::join!(
+        describe_task_definition(&profile, &region, &previous),
+        describe_task_definition(&profile, &region, &current)
+    );
+    Ok(diff(from?, to?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(name: &str, image: &str, environment: &[(&str, &str)]) -> ContainerSpec {
+        ContainerSpec {
+            name: name.to_string(),
+            image: image.to_string(),
+            essential: true,
+            cpu: 0,
+            memory: Some(512),
+            memory_reservation: None,
+            entry_point: Vec::new(),
+            command: Vec::new(),
+            environment: environment
+                .iter()
+                .map(|(name, value)| EnvironmentVariable {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            environment_files: Vec::new(),
+            secrets: Vec::new(),
+            port_mappings: Vec::new(),
+            log_driver: Some("awslogs".to_string()),
+            health_check: None,
+        }
+    }
+
+    fn task_definition(revision: i32, containers: Vec<ContainerSpec>) -> TaskDefinitionDetails {
+        TaskDefinitionDetails {
+            arn: format!(
+                "arn:aws:ecs:eu-west-1:123456789012:task-definition/api:{}",
+                revision
+            ),
+            family: "api".to_string(),
+            revision,
+            status: Some("ACTIVE".to_string()),
+            cpu: Some("256".to_string()),
+            memory: Some("512".to_string()),
+            network_mode: Some("awsvpc".to_string()),
+            requires_compatibilities: vec!["FARGATE".to_string()],
+            task_role_arn: None,
+            execution_role_arn: None,
+            registered_at: None,
+            registered_by: None,
+            containers,
+        }
+    }
+
+    fn changes(before: Value, after: Value) -> Vec<(String, &'static str)> {
+        let mut changes = Vec::new();
+        diff_values("", &before, &after, &mut changes);
+        changes
+            .into_iter()
+            .map(|c| {
+                let kind = match c.kind {
+                    ChangeKind::Added => "added",
+                    ChangeKind::Removed => "removed",
+                    ChangeKind::Changed => "changed",
+                };
+                (c.path, kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff_values_reports_nested_changes_by_path() {
+        let before =
+            json!({ "cpu": "256", "containers": { "web": { "image": "web:1", "port": 80 } } });
+        let after = json!({ "cpu": "512", "containers": { "web": { "image": "web:2", "log": "awslogs" } } });
+        assert_eq!(
+            changes(before, after),
+            vec![
+                ("containers.web.image".to_string(), "changed"),
+                ("containers.web.port".to_string(), "removed"),
+                ("containers.web.log".to_string(), "added"),
+                ("cpu".to_string(), "changed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_values_treats_null_as_unset() {
+        assert_eq!(
+            changes(
+                json!({ "role": null, "memory": "512" }),
+                json!({ "role": "arn", "memory": null })
+            ),
+            vec![
+                ("memory".to_string(), "removed"),
+                ("role".to_string(), "added"),
+            ]
+        );
+        assert!(changes(json!({ "role": null }), json!({})).is_empty());
+        assert!(changes(json!({ "a": [1, 2] }), json!({ "a": [1, 2] })).is_empty());
+    }
+
+    #[test]
+    fn diff_addresses_containers_and_variables_by_name() {
+        let from = task_definition(
+            1,
+            vec![
+                container("web", "web:1", &[("LOG_LEVEL", "info"), ("PORT", "80")]),
+                container("sidecar", "envoy:1", &[]),
+            ],
+        );
+        // Reordered containers and variables are not a change
+        let to = task_definition(
+            2,
+            vec![
+                container("sidecar", "envoy:1", &[]),
+                container("web", "web:1", &[("PORT", "80"), ("LOG_LEVEL", "debug")]),
+            ],
+        );
+
+        let diff = diff(from, to);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].path, "containers.web.environment.LOG_LEVEL");
+        assert_eq!(diff.changes[0].before, Some(json!("info")));
+        assert_eq!(diff.changes[0].after, Some(json!("debug")));
+    }
+
+    #[test]
+    fn diff_brackets_keys_with_dots() {
+        let from = task_definition(
+            1,
+            vec![container("web", "web:1", &[("spring.profiles", "a")])],
+        );
+        let to = task_definition(
+            2,
+            vec![container("web", "web:1", &[("spring.profiles", "b")])],
+        );
+
+        let diff = diff(from, to);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(
+            diff.changes[0].path,
+            "containers.web.environment[\"spring.profiles\"]"
+        );
+    }
+
+    #[test]
+    fn diff_keeps_port_mappings_on_the_same_container_port_apart() {
+        let port = |host_port: i32| PortMapping {
+            container_port: Some(8080),
+            host_port: Some(host_port),
+            protocol: Some("tcp".to_string()),
+            name: None,
+        };
+        let mut web = container("web", "web:1", &[]);
+        web.port_mappings = vec![port(8080), port(9090)];
+        let from = task_definition(1, vec![web.clone()]);
+        web.port_mappings = vec![port(8080)];
+        let to = task_definition(2, vec![web]);
+
+        let diff = diff(from, to);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(
+            diff.changes[0].path,
+            "containers.web.portMappings.9090:8080/tcp"
+        );
+        assert!(matches!(diff.changes[0].kind, ChangeKind::Removed));
+    }
+}
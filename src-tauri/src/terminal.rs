@@ -1,16 +1,87 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use tauri::{command, Emitter, Window};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{command, Emitter, Manager, Window};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::process::{Child, Command as TokioCommand};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::{audit, debug_tasks, ecs_api, shells, ssm, triggers};
+use crate::aws::{
+    cluster_name_from_arn, get_path_with_common_locations, task_id_from_arn, terminate_ssm_session,
+};
+use crate::ecs_api::{ExecConfiguration, ExecSession};
+use crate::exec::ExecTarget;
+use crate::paste::{self, PasteInfo, STDIN_CHUNK_DELAY, STDIN_CHUNK_SIZE};
 
 type SessionId = String;
+type GroupId = String;
 type Writer = mpsc::UnboundedSender<Vec<u8>>;
+type SessionReader = Box<dyn AsyncRead + Unpin + Send>;
+type SessionWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+// Output chunks buffered for backend observers of a session before they lag
+const OUTPUT_OBSERVER_CAPACITY: usize = 1024;
+
+// How long a new session's output is watched for its shell failing to start
+const SHELL_START_WAIT: Duration = Duration::from_secs(5);
+
+// Broadcast input is audited once per line, or after this much input without one
+const BROADCAST_AUDIT_BYTES: usize = 4096;
 
 struct TerminalState {
     writers: HashMap<SessionId, Writer>,
     processes: HashMap<SessionId, Child>,
+    native_sessions: HashMap<SessionId, ssm::SessionControl>,
+    session_infos: HashMap<SessionId, SessionInfo>,
+    outputs: HashMap<SessionId, broadcast::Sender<String>>,
+    targets: HashMap<SessionId, ExecTarget>,
+    groups: HashMap<GroupId, HashSet<SessionId>>,
+    broadcasts: HashMap<GroupId, BroadcastTally>,
+    production: HashSet<SessionId>,
+    pending_pastes: HashMap<String, PendingPaste>,
+}
+
+// Broadcast input to a group that has not been audited yet
+#[derive(Default)]
+struct BroadcastTally {
+    session_ids: HashSet<SessionId>,
+    held_session_ids: HashSet<SessionId>,
+    bytes: usize,
+    lines: usize,
+}
+
+impl BroadcastTally {
+    fn record(self, window: &Window, group_id: &str) {
+        audit::record(
+            window.app_handle(),
+            "broadcast",
+            json!({
+                "groupId": group_id,
+                "sessionIds": self.session_ids,
+                "heldSessionIds": self.held_session_ids,
+                // Only the size, the input may hold passwords or tokens
+                "bytes": self.bytes,
+                "lines": self.lines,
+            }),
+        );
+    }
+}
+
+struct PendingPaste {
+    session_id: SessionId,
+    data: Vec<u8>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PastePendingEvent {
+    paste_id: String,
+    session_id: String,
+    paste: PasteInfo,
 }
 
 impl TerminalState {
@@ -18,13 +89,127 @@ impl TerminalState {
         Self {
             writers: HashMap::new(),
             processes: HashMap::new(),
+            native_sessions: HashMap::new(),
+            session_infos: HashMap::new(),
+            outputs: HashMap::new(),
+            targets: HashMap::new(),
+            groups: HashMap::new(),
+            broadcasts: HashMap::new(),
+            production: HashSet::new(),
+            pending_pastes: HashMap::new(),
+        }
+    }
+
+    fn remove_session(&mut self, session_id: &str) -> Option<Child> {
+        self.writers.remove(session_id);
+        self.outputs.remove(session_id);
+        self.targets.remove(session_id);
+        self.session_infos.remove(session_id);
+        for members in self.groups.values_mut() {
+            members.remove(session_id);
+        }
+        self.production.remove(session_id);
+        self.pending_pastes.retain(|_, p| p.session_id != session_id);
+        if let Some(control) = self.native_sessions.remove(session_id) {
+            control.terminate();
+        }
+        self.processes.remove(session_id)
+    }
+
+    // Send typed input to a session. Multi-line pastes into production sessions are
+    // held until confirmed, returns false when the input was held.
+    fn deliver_input(&mut self, window: &Window, session_id: &str, data: &str) -> Result<bool, String> {
+        let writer = self.writers.get(session_id).ok_or("Session not found")?;
+
+        if self.production.contains(session_id) {
+            if let Some(paste) = paste::detect_paste(data).filter(|p| p.is_multiline()) {
+                let paste_id = uuid::Uuid::new_v4().to_string();
+                self.pending_pastes.insert(
+                    paste_id.clone(),
+                    PendingPaste {
+                        session_id: session_id.to_string(),
+                        data: data.as_bytes().to_vec(),
+                    },
+                );
+                let _ = window.emit(
+                    &format!("term:paste-pending:{}", session_id),
+                    PastePendingEvent {
+                        paste_id,
+                        session_id: session_id.to_string(),
+                        paste,
+                    },
+                );
+                return Ok(false);
+            }
         }
+
+        triggers::on_input(session_id, data);
+        writer
+            .send(data.as_bytes().to_vec())
+            .map_err(|e| format!("Failed to send data: {}", e))?;
+        Ok(true)
     }
 }
 
 static TERMINAL_STATE: Mutex<Option<TerminalState>> = Mutex::new(None);
 
+// The cluster, task and container a live session is attached to
+pub(crate) fn session_target(session_id: &str) -> Option<ExecTarget> {
+    let state = TERMINAL_STATE.lock().unwrap();
+    state.as_ref()?.targets.get(session_id).cloned()
+}
+
+// Receive a copy of a session's output from now on, closed when the session ends
+pub(crate) fn subscribe_output(session_id: &str) -> Option<broadcast::Receiver<String>> {
+    let state = TERMINAL_STATE.lock().unwrap();
+    state.as_ref()?.outputs.get(session_id).map(|tx| tx.subscribe())
+}
+
+// Queue bytes for a session's stdin
+pub(crate) fn send_to_session(session_id: &str, data: Vec<u8>) -> Result<(), String> {
+    let state = TERMINAL_STATE.lock().unwrap();
+    if let Some(ref state) = *state {
+        if let Some(writer) = state.writers.get(session_id) {
+            triggers::on_input(session_id, &String::from_utf8_lossy(&data));
+            writer
+                .send(data)
+                .map_err(|e| format!("Failed to send data: {}", e))?;
+            return Ok(());
+        }
+    }
+    Err("Session not found".to_string())
+}
+
+// How an interactive session reaches the container
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionTransport {
+    // session-manager-plugin, started with the session from our own ExecuteCommand call
+    #[default]
+    Plugin,
+    // Built-in Session Manager client, no plugin needed. Does not support KMS-encrypted sessions.
+    Native,
+}
+
+// Session Manager details of a live session, for diagnostics. The session token is left out.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    session_id: String,
+    stream_url: String,
+    cluster_arn: Option<String>,
+    task_arn: Option<String>,
+    container_name: Option<String>,
+    container_runtime_id: Option<String>,
+    transport: SessionTransport,
+    // May differ from the shell requested when the container did not have it
+    pub(crate) shell_cmd: String,
+    // KMS and logging settings of the cluster, when they could be read
+    exec_configuration: Option<ExecConfiguration>,
+}
+
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn start_exec_session(
     window: Window,
     session_id: String,
@@ -34,79 +219,281 @@ pub async fn start_exec_session(
     task: String,
     container: String,
     shell_cmd: String,
-) -> Result<(), String> {
-    // Get PATH with common locations
-    let path = {
-        let mut paths = Vec::new();
-        if let Ok(existing_path) = std::env::var("PATH") {
-            paths.push(existing_path);
-        }
-        #[cfg(target_os = "macos")]
-        {
-            paths.push("/usr/local/bin".to_string());
-            paths.push("/opt/homebrew/bin".to_string());
-            paths.push("/opt/homebrew/opt/awscli/bin".to_string());
-            paths.push("/usr/bin".to_string());
-            paths.push("/bin".to_string());
-        }
-        #[cfg(target_os = "linux")]
-        {
-            paths.push("/usr/local/bin".to_string());
-            paths.push("/usr/bin".to_string());
-            paths.push("/bin".to_string());
-        }
-        #[cfg(target_os = "windows")]
-        {
-            if let Ok(program_files) = std::env::var("ProgramFiles") {
-                paths.push(format!("{}\\Amazon\\AWSCLIV2", program_files));
-            }
-        }
-        paths.join(if cfg!(target_os = "windows") { ";" } else { ":" })
+    transport: Option<SessionTransport>,
+) -> Result<SessionInfo, String> {
+    let target = ExecTarget {
+        profile,
+        region,
+        cluster,
+        task,
+        container,
     };
-    
-    let mut cmd = TokioCommand::new("aws");
-    cmd.env("PATH", &path)
-        .arg("ecs")
-        .arg("execute-command")
-        .arg("--cluster")
-        .arg(&cluster)
-        .arg("--task")
-        .arg(&task)
-        .arg("--container")
-        .arg(&container)
-        .arg("--command")
-        .arg(&shell_cmd)
-        .arg("--interactive")
-        .arg("--region")
-        .arg(&region)
-        .arg("--profile")
-        .arg(&profile);
-
-    let mut child = cmd
+    spawn_exec_session(
+        window,
+        session_id,
+        target,
+        Some(shell_cmd),
+        Vec::new(),
+        false,
+        transport.unwrap_or_default(),
+    )
+    .await
+}
+
+// What keeps a session alive, so it can be awaited and torn down
+enum SessionProcess {
+    Plugin(Child),
+    Native(ssm::SessionControl, oneshot::Receiver<Result<(), String>>),
+}
+
+// Hand an ExecuteCommand session straight to session-manager-plugin, the same
+// way the AWS CLI would but without its start-up cost
+fn spawn_plugin_session(
+    target: &ExecTarget,
+    session: &ExecSession,
+    runtime_id: &str,
+) -> Result<(SessionReader, SessionReader, SessionWriter, SessionProcess), String> {
+    let session_json = json!({
+        "sessionId": session.session_id,
+        "streamUrl": session.stream_url,
+        "tokenValue": session.token_value,
+    });
+    let parameters = json!({
+        "Target": format!(
+            "ecs:{}_{}_{}",
+            cluster_name_from_arn(&target.cluster),
+            task_id_from_arn(&target.task),
+            runtime_id
+        ),
+    });
+
+    let mut child = TokioCommand::new("session-manager-plugin")
+        .env("PATH", get_path_with_common_locations())
+        .arg(session_json.to_string())
+        .arg(&target.region)
+        .arg("StartSession")
+        .arg(&target.profile)
+        .arg(parameters.to_string())
+        .arg(ecs_api::ecs_endpoint(&target.region))
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .spawn()
-        .map_err(|e| format!("Failed to spawn process: {}", e))?;
+        .map_err(|e| format!("Failed to start session-manager-plugin: {}", e))?;
+
+    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+    let stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+
+    Ok((
+        Box::new(stdout),
+        Box::new(stderr),
+        Box::new(stdin),
+        SessionProcess::Plugin(child),
+    ))
+}
+
+// Why a session could not be opened
+enum StartError {
+    // The container could not exec the shell, with what the session printed
+    ShellMissing(String),
+    Failed(String),
+}
+
+impl From<String> for StartError {
+    fn from(e: String) -> Self {
+        StartError::Failed(e)
+    }
+}
+
+impl From<StartError> for String {
+    fn from(e: StartError) -> Self {
+        match e {
+            StartError::ShellMissing(output) => output,
+            StartError::Failed(e) => e,
+        }
+    }
+}
+
+// Start an interactive session, typing each init command in turn as the shell prompt appears.
+// Without a shell the best one in the container is used, and a requested shell the
+// container turns out not to have is swapped for it.
+pub(crate) async fn spawn_exec_session(
+    window: Window,
+    session_id: String,
+    target: ExecTarget,
+    shell_cmd: Option<String>,
+    init_commands: Vec<String>,
+    production: bool,
+    transport: SessionTransport,
+) -> Result<SessionInfo, String> {
+    let app = window.app_handle().clone();
+    let requested = match shell_cmd {
+        Some(shell_cmd) if !shells::wants_auto_shell(&shell_cmd) => shell_cmd,
+        _ => shells::resolve_shell(&app, &target).await?,
+    };
+
+    let opened = open_session(
+        window.clone(),
+        session_id.clone(),
+        target.clone(),
+        requested.clone(),
+        init_commands.clone(),
+        production,
+        transport,
+    )
+    .await;
+    let output = match opened {
+        Err(StartError::ShellMissing(output)) => output,
+        opened => return opened.map_err(String::from),
+    };
+
+    let shell_cmd = shells::fallback_shell(&app, &target, &requested)
+        .await
+        .map_err(|e| format!("{} ({})", output, e))?;
+    eprintln!(
+        "[DEBUG] {} is not available in {}, retrying with {}",
+        requested, target.container, shell_cmd
+    );
+    open_session(
+        window,
+        session_id,
+        target,
+        shell_cmd,
+        init_commands,
+        production,
+        transport,
+    )
+    .await
+    .map_err(String::from)
+}
 
-    let mut stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-    let mut stderr = child.stderr.take().ok_or("Failed to get stderr")?;
-    let mut stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+async fn open_session(
+    window: Window,
+    session_id: String,
+    target: ExecTarget,
+    shell_cmd: String,
+    init_commands: Vec<String>,
+    production: bool,
+    transport: SessionTransport,
+) -> Result<SessionInfo, StartError> {
+    let runtime_id = async {
+        match transport {
+            SessionTransport::Plugin => ecs_api::container_runtime_id(&target).await.map(Some),
+            SessionTransport::Native => Ok(None),
+        }
+    };
+    let (runtime_id, exec_configuration) = tokio::join!(
+        runtime_id,
+        ecs_api::exec_configuration(&target.profile, &target.region, &target.cluster),
+    );
+    let runtime_id = runtime_id?;
+    // Only informational, the session works without it
+    let exec_configuration = match exec_configuration {
+        Ok(config) => Some(config),
+        Err(e) => {
+            eprintln!("[DEBUG] Failed to read exec configuration: {}", e);
+            None
+        }
+    };
+    // Refuse before ExecuteCommand so no session is left open on the SSM side
+    if let SessionTransport::Native = transport {
+        let kms_key = exec_configuration
+            .as_ref()
+            .and_then(|c| c.kms_key_id.as_ref());
+        if let Some(kms_key) = kms_key {
+            return Err(format!(
+                "Cluster encrypts sessions with KMS key {}, use the plugin transport",
+                kms_key
+            )
+            .into());
+        }
+    }
+
+    let session = ecs_api::execute_command(&target, &shell_cmd, true).await?;
+    eprintln!("[DEBUG] Opening session {} to {}", session.session_id, target.task);
+
+    let info = SessionInfo {
+        session_id: session.session_id.clone(),
+        stream_url: session.stream_url.clone(),
+        cluster_arn: session.cluster_arn.clone(),
+        task_arn: session.task_arn.clone(),
+        container_name: session.container_name.clone(),
+        container_runtime_id: runtime_id.clone(),
+        transport,
+        shell_cmd: shell_cmd.clone(),
+        exec_configuration,
+    };
+
+    let connected = match transport {
+        SessionTransport::Plugin => {
+            spawn_plugin_session(&target, &session, runtime_id.as_deref().unwrap_or_default())
+        }
+        SessionTransport::Native => ssm::connect(&session.stream_url, &session.token_value)
+            .await
+            .map(|native| {
+                let (output, input) = tokio::io::split(native.terminal);
+                (
+                    Box::new(output) as SessionReader,
+                    Box::new(native.errors) as SessionReader,
+                    Box::new(input) as SessionWriter,
+                    SessionProcess::Native(native.control, native.finished),
+                )
+            }),
+    };
+    let (mut stdout, mut stderr, mut stdin, process) = match connected {
+        Ok(connected) => connected,
+        Err(e) => {
+            if let Err(terminate_error) =
+                terminate_ssm_session(&target.profile, &target.region, &session.session_id).await
+            {
+                eprintln!(
+                    "[DEBUG] Failed to terminate session {}: {}",
+                    session.session_id, terminate_error
+                );
+            }
+            return Err(e.into());
+        }
+    };
 
     let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let init_tx = tx.clone();
+    let mut init_queue: VecDeque<String> = init_commands.into();
+    let (output_tx, _) = broadcast::channel::<String>(OUTPUT_OBSERVER_CAPACITY);
 
-    {
+    let native_finished = {
         let mut state = TERMINAL_STATE.lock().unwrap();
         if state.is_none() {
             *state = Some(TerminalState::new());
         }
         let state = state.as_mut().unwrap();
         state.writers.insert(session_id.clone(), tx);
-        state.processes.insert(session_id.clone(), child);
-    }
+        state.outputs.insert(session_id.clone(), output_tx.clone());
+        state.targets.insert(session_id.clone(), target);
+        state.session_infos.insert(session_id.clone(), info.clone());
+        // Before any input can arrive, so the paste guard covers the first keystroke
+        if production {
+            state.production.insert(session_id.clone());
+        }
+        match process {
+            SessionProcess::Plugin(child) => {
+                state.processes.insert(session_id.clone(), child);
+                None
+            }
+            SessionProcess::Native(control, finished) => {
+                state.native_sessions.insert(session_id.clone(), control);
+                Some(finished)
+            }
+        }
+    };
 
     let window_clone = window.clone();
     let session_id_clone = session_id.clone();
+    // Output up to the first prompt, checked for the shell failing to exec
+    let (started_tx, started_rx) = oneshot::channel::<Option<String>>();
+    let mut starting = Some((started_tx, String::new()));
+    // Set when this attempt is given up on, its session id may be reused straight away
+    let abandoned = Arc::new(AtomicBool::new(false));
 
     tokio::spawn(async move {
         let mut stdout_buf = [0u8; 8192];
@@ -118,6 +505,26 @@ pub async fn start_exec_session(
                         Ok(0) => break,
                         Ok(n) => {
                             let data = String::from_utf8_lossy(&stdout_buf[..n]).to_string();
+                            if let Some((started_tx, mut early)) = starting.take() {
+                                early.push_str(&data);
+                                if started_tx.is_closed() {
+                                    // Nobody waits any more, the output goes to the terminal as is
+                                } else if shells::shell_missing(&early) {
+                                    let _ = started_tx.send(Some(strip_ansi(&early).trim().to_string()));
+                                    break;
+                                } else if looks_like_prompt(&data) {
+                                    let _ = started_tx.send(None);
+                                } else {
+                                    starting = Some((started_tx, early));
+                                }
+                            }
+                            if !init_queue.is_empty() && looks_like_prompt(&data) {
+                                if let Some(init) = init_queue.pop_front() {
+                                    let _ = init_tx.send(format!("{}\r", init).into_bytes());
+                                }
+                            }
+                            triggers::on_output(&window_clone, &session_id_clone, &data);
+                            let _ = output_tx.send(data.clone());
                             let _ = window_clone.emit(&format!("term:data:{}", session_id_clone), data);
                         }
                         Err(_) => break,
@@ -139,47 +546,268 @@ pub async fn start_exec_session(
 
     tokio::spawn(async move {
         while let Some(data) = rx.recv().await {
-            if stdin.write_all(&data).await.is_err() {
-                break;
+            // Feed large writes in rate-limited chunks, big bursts get garbled over SSM
+            let chunked = data.len() > STDIN_CHUNK_SIZE;
+            for chunk in data.chunks(STDIN_CHUNK_SIZE) {
+                if stdin.write_all(chunk).await.is_err() || stdin.flush().await.is_err() {
+                    return;
+                }
+                if chunked {
+                    tokio::time::sleep(STDIN_CHUNK_DELAY).await;
+                }
             }
         }
     });
 
     let window_clone = window.clone();
     let session_id_clone = session_id.clone();
+    let abandoned_clone = abandoned.clone();
 
     tokio::spawn(async move {
-        let process_to_wait = {
+        if let Some(finished) = native_finished {
+            let result = finished.await;
+            if abandoned_clone.load(Ordering::SeqCst) {
+                return;
+            }
+            if let Ok(Err(e)) = result {
+                let _ = window_clone.emit(&format!("term:error:{}", session_id_clone), format!("{}\r\n", e));
+            }
             let mut state = TERMINAL_STATE.lock().unwrap();
             if let Some(ref mut state) = *state {
-                state.processes.remove(&session_id_clone)
-            } else {
-                None
+                state.native_sessions.remove(&session_id_clone);
             }
-        };
+        } else {
+            let process_to_wait = {
+                let mut state = TERMINAL_STATE.lock().unwrap();
+                match *state {
+                    Some(ref mut state) if !abandoned_clone.load(Ordering::SeqCst) => {
+                        state.processes.remove(&session_id_clone)
+                    }
+                    _ => None,
+                }
+            };
 
-        if let Some(mut process) = process_to_wait {
-            let _ = process.wait().await;
+            if let Some(mut process) = process_to_wait {
+                let _ = process.wait().await;
+            }
         }
 
+        if abandoned_clone.load(Ordering::SeqCst) {
+            return;
+        }
+        // Dropping the sender lets scripts waiting on output see the session end
+        if let Some(ref mut state) = *TERMINAL_STATE.lock().unwrap() {
+            state.outputs.remove(&session_id_clone);
+        }
+        triggers::remove_session(&session_id_clone);
+        debug_tasks::session_closed(&session_id_clone);
         let _ = window_clone.emit(&format!("term:exit:{}", session_id_clone), ());
     });
 
+    // A shell the container lacks ends the session right away, the attempt is
+    // torn down quietly so it can be retried under the same id
+    if let Ok(Ok(Some(output))) = tokio::time::timeout(SHELL_START_WAIT, started_rx).await {
+        abandoned.store(true, Ordering::SeqCst);
+        let process = TERMINAL_STATE
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|state| state.remove_session(&session_id));
+        if let Some(mut process) = process {
+            let _ = process.start_kill();
+        }
+        return Err(StartError::ShellMissing(output));
+    }
+
+    Ok(info)
+}
+
+// Drop ANSI escape sequences (colours, cursor moves, title updates) from terminal output
+pub(crate) fn strip_ansi(data: &str) -> String {
+    let mut plain = String::with_capacity(data.len());
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            match chars.next() {
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+// Whether a chunk of output ends with something shaped like a shell prompt ("$ ", "# ", "> ")
+pub(crate) fn looks_like_prompt(data: &str) -> bool {
+    let plain = strip_ansi(data);
+    let last_line = plain.rsplit(['\n', '\r']).next().unwrap_or("");
+    matches!(last_line.trim_end().chars().last(), Some('$' | '#' | '>' | '%'))
+        && last_line.ends_with(' ')
+}
+
+#[command]
+pub fn write_exec_stdin(
+    window: Window,
+    session_id: Option<String>,
+    group_id: Option<String>,
+    data: String,
+) -> Result<(), String> {
+    if let Some(group_id) = group_id {
+        return broadcast_exec_stdin(&window, &group_id, data);
+    }
+
+    let session_id = session_id.ok_or("Either a session or a group must be given")?;
+    let mut state = TERMINAL_STATE.lock().unwrap();
+    let state = state.as_mut().ok_or("Session not found")?;
+    state.deliver_input(&window, &session_id, &data).map(|_| ())
+}
+
+fn broadcast_exec_stdin(window: &Window, group_id: &str, data: String) -> Result<(), String> {
+    let (live, finished) = {
+        let mut state = TERMINAL_STATE.lock().unwrap();
+        let state = state.as_mut().ok_or("Session group not found")?;
+        let members: Vec<SessionId> = state
+            .groups
+            .get(group_id)
+            .ok_or("Session group not found")?
+            .iter()
+            .cloned()
+            .collect();
+
+        let mut delivered = Vec::new();
+        let mut held = Vec::new();
+        for session_id in members {
+            match state.deliver_input(window, &session_id, &data) {
+                Ok(true) => delivered.push(session_id),
+                Ok(false) => held.push(session_id),
+                Err(_) => {}
+            }
+        }
+
+        let live = !delivered.is_empty() || !held.is_empty();
+        let mut finished = None;
+        if live {
+            // Keystrokes are tallied and written out a line at a time
+            let tally = state.broadcasts.entry(group_id.to_string()).or_default();
+            tally.session_ids.extend(delivered);
+            tally.held_session_ids.extend(held);
+            tally.bytes += data.len();
+            let lines = data.matches(['\r', '\n']).count();
+            tally.lines += lines;
+            if lines > 0 || tally.bytes >= BROADCAST_AUDIT_BYTES {
+                finished = state.broadcasts.remove(group_id);
+            }
+        }
+        (live, finished)
+    };
+
+    if let Some(tally) = finished {
+        tally.record(window, group_id);
+    }
+
+    if !live {
+        return Err("No live sessions in group".to_string());
+    }
+    Ok(())
+}
+
+#[command]
+pub fn set_session_production(session_id: String, production: bool) -> Result<(), String> {
+    let mut state = TERMINAL_STATE.lock().unwrap();
+    let state = state.as_mut().ok_or("Session not found")?;
+    if !state.writers.contains_key(&session_id) {
+        return Err("Session not found".to_string());
+    }
+    if production {
+        state.production.insert(session_id);
+    } else {
+        state.production.remove(&session_id);
+    }
     Ok(())
 }
 
 #[command]
-pub fn write_exec_stdin(session_id: String, data: String) -> Result<(), String> {
-    let state = TERMINAL_STATE.lock().unwrap();
-    if let Some(ref state) = *state {
-        if let Some(writer) = state.writers.get(&session_id) {
-            writer
-                .send(data.into_bytes())
-                .map_err(|e| format!("Failed to send data: {}", e))?;
+pub fn confirm_paste(window: Window, paste_id: String) -> Result<(), String> {
+    let pending = {
+        let mut state = TERMINAL_STATE.lock().unwrap();
+        let state = state.as_mut().ok_or("Paste not found")?;
+        state.pending_pastes.remove(&paste_id).ok_or("Paste not found")?
+    };
+
+    audit::record(
+        window.app_handle(),
+        "paste_confirmed",
+        json!({
+            "sessionId": pending.session_id,
+            "bytes": pending.data.len(),
+        }),
+    );
+
+    send_to_session(&pending.session_id, pending.data)
+}
+
+#[command]
+pub fn discard_paste(paste_id: String) -> Result<(), String> {
+    let mut state = TERMINAL_STATE.lock().unwrap();
+    if let Some(ref mut state) = *state {
+        state.pending_pastes.remove(&paste_id);
+    }
+    Ok(())
+}
+
+#[command]
+pub fn set_session_group(group_id: String, session_ids: Vec<String>) -> Result<(), String> {
+    let mut state = TERMINAL_STATE.lock().unwrap();
+    let state = state.get_or_insert_with(TerminalState::new);
+    let members = session_ids
+        .into_iter()
+        .filter(|id| state.writers.contains_key(id))
+        .collect();
+    state.groups.insert(group_id, members);
+    Ok(())
+}
+
+#[command]
+pub fn remove_from_session_group(group_id: String, session_id: String) -> Result<(), String> {
+    let mut state = TERMINAL_STATE.lock().unwrap();
+    if let Some(ref mut state) = *state {
+        if let Some(members) = state.groups.get_mut(&group_id) {
+            members.remove(&session_id);
             return Ok(());
         }
     }
-    Err("Session not found".to_string())
+    Err("Session group not found".to_string())
+}
+
+#[command]
+pub fn delete_session_group(window: Window, group_id: String) -> Result<(), String> {
+    let unrecorded = {
+        let mut state = TERMINAL_STATE.lock().unwrap();
+        state.as_mut().and_then(|state| {
+            state.groups.remove(&group_id);
+            state.broadcasts.remove(&group_id)
+        })
+    };
+    // Input typed since the last full line
+    if let Some(tally) = unrecorded {
+        tally.record(&window, &group_id);
+    }
+    Ok(())
 }
 
 #[command]
@@ -187,13 +815,14 @@ pub async fn close_exec_session(session_id: String) -> Result<(), String> {
     let process_to_kill = {
         let mut state = TERMINAL_STATE.lock().unwrap();
         if let Some(ref mut state) = *state {
-            state.writers.remove(&session_id);
-            state.processes.remove(&session_id)
+            state.remove_session(&session_id)
         } else {
             None
         }
     };
 
+    triggers::remove_session(&session_id);
+
     if let Some(mut process) = process_to_kill {
         let _ = process.kill().await;
     }
@@ -201,3 +830,48 @@ pub async fn close_exec_session(session_id: String) -> Result<(), String> {
     Ok(())
 }
 
+
+// Only native sessions can be resized, plugin sessions keep the size they started with
+#[command]
+pub fn resize_exec_session(session_id: String, cols: u16, rows: u16) -> Result<(), String> {
+    let state = TERMINAL_STATE.lock().unwrap();
+    let state = state.as_ref().ok_or("Session not found")?;
+    match state.native_sessions.get(&session_id) {
+        Some(control) => control.resize(cols, rows),
+        None if state.writers.contains_key(&session_id) => Ok(()),
+        None => Err("Session not found".to_string()),
+    }
+}
+
+#[command]
+pub fn get_session_info(session_id: String) -> Result<SessionInfo, String> {
+    let state = TERMINAL_STATE.lock().unwrap();
+    state
+        .as_ref()
+        .and_then(|s| s.session_infos.get(&session_id))
+        .cloned()
+        .ok_or("Session not found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_like_prompt_matches_common_prompts() {
+        assert!(looks_like_prompt("user@host:~$ "));
+        assert!(looks_like_prompt("output\r\n/app # "));
+        assert!(looks_like_prompt("PS C:\\> "));
+        assert!(looks_like_prompt(
+            "\x1b[01;32mroot@web\x1b[00m:\x1b[01;34m/\x1b[00m# "
+        ));
+    }
+
+    #[test]
+    fn looks_like_prompt_ignores_other_output() {
+        assert!(!looks_like_prompt("user@host:~$"));
+        assert!(!looks_like_prompt("$ \r\nstill running"));
+        assert!(!looks_like_prompt("done\n"));
+        assert!(!looks_like_prompt(""));
+    }
+}
@@ -0,0 +1,221 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, Emitter, Window};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::terminal::{looks_like_prompt, strip_ansi};
+
+// Longest partial line kept between output chunks for regex matching
+const MAX_PENDING_LINE: usize = 4096;
+// Quiet period after a rule fires, unless the rule sets its own
+const DEFAULT_COOLDOWN_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TriggerCondition {
+    // A line of output matches the pattern
+    #[serde(rename_all = "camelCase")]
+    Regex { pattern: String },
+    // The prompt came back after a command that ran at least this long
+    #[serde(rename_all = "camelCase")]
+    CommandFinished { min_duration_secs: u64 },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TriggerAction {
+    Notify,
+    MarkTab,
+    StopRecording,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerRule {
+    id: String,
+    name: String,
+    condition: TriggerCondition,
+    actions: Vec<TriggerAction>,
+    // Disable the rule after it fires once
+    #[serde(default)]
+    once: bool,
+    // Matches within this long of the rule last firing are ignored, so a noisy
+    // pattern does not flood the notification centre
+    #[serde(default = "default_cooldown_secs")]
+    cooldown_secs: u64,
+}
+
+fn default_cooldown_secs() -> u64 {
+    DEFAULT_COOLDOWN_SECS
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TriggerFiredEvent {
+    rule_id: String,
+    name: String,
+    actions: Vec<TriggerAction>,
+    detail: String,
+}
+
+struct ActiveRule {
+    rule: TriggerRule,
+    regex: Option<Regex>,
+    last_fired: Option<Instant>,
+}
+
+impl ActiveRule {
+    fn ready(&self) -> bool {
+        match self.last_fired {
+            Some(_) if self.rule.once => false,
+            Some(at) => at.elapsed() >= Duration::from_secs(self.rule.cooldown_secs),
+            None => true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SessionTriggers {
+    rules: Vec<ActiveRule>,
+    // Incomplete last line of output, completed by the next chunk
+    pending_line: String,
+    // When Enter was last sent, i.e. when the running command started
+    command_started: Option<Instant>,
+}
+
+static TRIGGERS: Mutex<Option<HashMap<String, SessionTriggers>>> = Mutex::new(None);
+
+// Track Enter presses so command durations can be measured
+pub(crate) fn on_input(session_id: &str, data: &str) {
+    if !data.contains(['\r', '\n']) {
+        return;
+    }
+    let mut triggers = TRIGGERS.lock().unwrap();
+    if let Some(session) = triggers.as_mut().and_then(|t| t.get_mut(session_id)) {
+        session.command_started = Some(Instant::now());
+    }
+}
+
+// Evaluate a session's watch rules against a chunk of output
+pub(crate) fn on_output(window: &Window, session_id: &str, data: &str) {
+    let fired = {
+        let mut triggers = TRIGGERS.lock().unwrap();
+        let Some(session) = triggers.as_mut().and_then(|t| t.get_mut(session_id)) else {
+            return;
+        };
+        session.evaluate(data)
+    };
+
+    for (rule, detail) in fired {
+        fire(window, session_id, rule, detail);
+    }
+}
+
+pub(crate) fn remove_session(session_id: &str) {
+    let mut triggers = TRIGGERS.lock().unwrap();
+    if let Some(ref mut triggers) = *triggers {
+        triggers.remove(session_id);
+    }
+}
+
+impl SessionTriggers {
+    fn evaluate(&mut self, data: &str) -> Vec<(TriggerRule, String)> {
+        let mut fired = Vec::new();
+
+        let text = format!("{}{}", self.pending_line, strip_ansi(data));
+        let mut lines: Vec<&str> = text.split(['\n', '\r']).collect();
+        let partial = lines.pop().unwrap_or("");
+
+        for rule in self.rules.iter_mut().filter(|r| r.ready()) {
+            if let Some(ref regex) = rule.regex {
+                if let Some(line) = lines.iter().find(|line| regex.is_match(line)) {
+                    rule.last_fired = Some(Instant::now());
+                    fired.push((rule.rule.clone(), line.trim().to_string()));
+                }
+            }
+        }
+
+        let start = partial.len().saturating_sub(MAX_PENDING_LINE);
+        let start = (start..partial.len())
+            .find(|i| partial.is_char_boundary(*i))
+            .unwrap_or(partial.len());
+        self.pending_line = partial[start..].to_string();
+
+        if looks_like_prompt(data) {
+            if let Some(started) = self.command_started.take() {
+                let elapsed = started.elapsed();
+                for rule in self.rules.iter_mut().filter(|r| r.ready()) {
+                    if let TriggerCondition::CommandFinished { min_duration_secs } = rule.rule.condition {
+                        if elapsed >= Duration::from_secs(min_duration_secs) {
+                            rule.last_fired = Some(Instant::now());
+                            fired.push((
+                                rule.rule.clone(),
+                                format!("Command finished after {}s", elapsed.as_secs()),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        fired
+    }
+}
+
+fn fire(window: &Window, session_id: &str, rule: TriggerRule, detail: String) {
+    if rule.actions.contains(&TriggerAction::Notify) {
+        let _ = window
+            .notification()
+            .builder()
+            .title(&rule.name)
+            .body(&detail)
+            .show();
+    }
+
+    // Marking the tab and stopping a recording are handled by the UI
+    let _ = window.emit(
+        &format!("term:trigger:{}", session_id),
+        TriggerFiredEvent {
+            rule_id: rule.id,
+            name: rule.name,
+            actions: rule.actions,
+            detail,
+        },
+    );
+}
+
+#[command]
+pub fn set_session_triggers(session_id: String, rules: Vec<TriggerRule>) -> Result<(), String> {
+    let mut active = Vec::new();
+    for rule in rules {
+        let regex = match rule.condition {
+            TriggerCondition::Regex { ref pattern } => Some(
+                Regex::new(pattern)
+                    .map_err(|e| format!("Invalid pattern in rule '{}': {}", rule.name, e))?,
+            ),
+            TriggerCondition::CommandFinished { .. } => None,
+        };
+        active.push(ActiveRule {
+            rule,
+            regex,
+            last_fired: None,
+        });
+    }
+
+    let mut triggers = TRIGGERS.lock().unwrap();
+    let session = triggers
+        .get_or_insert_with(HashMap::new)
+        .entry(session_id)
+        .or_default();
+    session.rules = active;
+    Ok(())
+}
+
+#[command]
+pub fn clear_session_triggers(session_id: String) -> Result<(), String> {
+    remove_session(&session_id);
+    Ok(())
+}
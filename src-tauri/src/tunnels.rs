@@ -0,0 +1,663 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, Window};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command as TokioCommand;
+use tokio::sync::oneshot;
+
+use crate::aws::{
+    cluster_name_from_arn, describe_running_tasks, get_path_with_common_locations, run_json_async,
+    task_id_from_arn,
+};
+use crate::storage;
+
+const TUNNELS_STORE: &str = ".tunnels.dat";
+const TUNNELS_KEY: &str = "tunnels";
+const STACKS_KEY: &str = "stacks";
+const PORT_FORWARD_DOCUMENT: &str = "AWS-StartPortForwardingSessionToRemoteHost";
+// Printed by session-manager-plugin once the local port accepts connections
+const READY_MARKER: &str = "Waiting for connections";
+// How often a connected tunnel checks that its task is still running
+const HEALTH_INTERVAL: Duration = Duration::from_secs(20);
+const RETRY_MIN: Duration = Duration::from_secs(5);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+
+// Guards read-modify-write of the tunnels store
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+static RUNNING_TUNNELS: Mutex<Option<HashMap<String, RunningTunnel>>> = Mutex::new(None);
+// Tells supervisors of a stopped tunnel apart from one started again in its place
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelDefinition {
+    #[serde(default)]
+    id: String,
+    name: String,
+    profile: String,
+    region: String,
+    cluster: String,
+    // Forward through a running task of this service
+    service: String,
+    // Container whose agent carries the session, defaults to the first one with a runtime id
+    #[serde(default)]
+    container: Option<String>,
+    remote_host: String,
+    remote_port: u16,
+    // Used when free, otherwise the OS picks a port
+    local_port: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelStack {
+    #[serde(default)]
+    id: String,
+    name: String,
+    tunnel_ids: Vec<String>,
+    // Start the stack when the app launches
+    #[serde(default)]
+    auto_start: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TunnelState {
+    Starting,
+    Connected,
+    // Lost its session or task, retrying against a freshly resolved task
+    Reconnecting,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelStatus {
+    tunnel_id: String,
+    name: String,
+    state: TunnelState,
+    task: Option<String>,
+    local_port: Option<u16>,
+    restarts: u32,
+    last_error: Option<String>,
+    connected_since: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StackHealth {
+    // Every tunnel is connected
+    Healthy,
+    // Some tunnels are connected
+    Degraded,
+    // Running but nothing connected
+    Down,
+    Stopped,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackStatus {
+    stack_id: String,
+    name: String,
+    health: StackHealth,
+    tunnels: Vec<TunnelStatus>,
+}
+
+struct RunningTunnel {
+    run_id: u64,
+    status: TunnelStatus,
+    stop: Option<oneshot::Sender<()>>,
+}
+
+// Why a single port forwarding session ended
+enum SessionEnd {
+    Stopped,
+    Lost { error: String, was_connected: bool },
+}
+
+fn load_tunnels(app: &AppHandle) -> Result<Vec<TunnelDefinition>, String> {
+    storage::load(app, TUNNELS_STORE, TUNNELS_KEY)
+}
+
+fn load_stacks(app: &AppHandle) -> Result<Vec<TunnelStack>, String> {
+    storage::load(app, TUNNELS_STORE, STACKS_KEY)
+}
+
+fn stopped_status(tunnel: &TunnelDefinition) -> TunnelStatus {
+    TunnelStatus {
+        tunnel_id: tunnel.id.clone(),
+        name: tunnel.name.clone(),
+        state: TunnelState::Stopped,
+        task: None,
+        local_port: None,
+        restarts: 0,
+        last_error: None,
+        connected_since: None,
+    }
+}
+
+fn current_status(tunnel: &TunnelDefinition) -> TunnelStatus {
+    let running = RUNNING_TUNNELS.lock().unwrap();
+    running
+        .as_ref()
+        .and_then(|r| r.get(&tunnel.id))
+        .map(|t| t.status.clone())
+        .unwrap_or_else(|| stopped_status(tunnel))
+}
+
+// Apply a change to a running tunnel's status and publish it, ignored once the
+// run has been stopped or replaced
+fn update_status(
+    app: &AppHandle,
+    tunnel_id: &str,
+    run_id: u64,
+    change: impl FnOnce(&mut TunnelStatus),
+) {
+    let status = {
+        let mut running = RUNNING_TUNNELS.lock().unwrap();
+        let Some(tunnel) = running.as_mut().and_then(|r| r.get_mut(tunnel_id)) else {
+            return;
+        };
+        if tunnel.run_id != run_id {
+            return;
+        }
+        change(&mut tunnel.status);
+        tunnel.status.clone()
+    };
+    let _ = app.emit("tunnel:status", status);
+}
+
+// Find a running task of the service and the runtime id of the container the
+// session is carried by
+async fn resolve_target(tunnel: &TunnelDefinition) -> Result<(String, String), String> {
+    let tasks = describe_running_tasks(
+        &tunnel.profile,
+        &tunnel.region,
+        &tunnel.cluster,
+        Some(&tunnel.service),
+    )
+    .await?;
+    if tasks.is_empty() {
+        return Err(format!("No running tasks for service {}", tunnel.service));
+    }
+
+    tasks
+        .iter()
+        .find_map(|task| {
+            let containers = task["containers"].as_array()?;
+            let runtime_id = containers
+                .iter()
+                .filter(|c| match tunnel.container {
+                    Some(ref name) => c["name"].as_str() == Some(name.as_str()),
+                    None => true,
+                })
+                .find_map(|c| c["runtimeId"].as_str())?;
+            Some((
+                task["taskArn"].as_str()?.to_string(),
+                runtime_id.to_string(),
+            ))
+        })
+        .ok_or_else(|| {
+            format!(
+                "No running task of service {} has a container to forward through",
+                tunnel.service
+            )
+        })
+}
+
+async fn task_running(tunnel: &TunnelDefinition, task_arn: &str) -> bool {
+    let v = run_json_async(
+        "aws",
+        &[
+            "ecs",
+            "describe-tasks",
+            "--cluster",
+            &tunnel.cluster,
+            "--tasks",
+            task_arn,
+            "--region",
+            &tunnel.region,
+            "--profile",
+            &tunnel.profile,
+            "--output",
+            "json",
+        ],
+    )
+    .await;
+    match v {
+        Ok(v) => v["tasks"][0]["lastStatus"].as_str() == Some("RUNNING"),
+        // A failed lookup is not proof the task went away
+        Err(e) => {
+            eprintln!("[DEBUG] Tunnel health check failed for {}: {}", task_arn, e);
+            true
+        }
+    }
+}
+
+fn pick_local_port(preferred: u16) -> Result<u16, String> {
+    if preferred != 0 && std::net::TcpListener::bind(("127.0.0.1", preferred)).is_ok() {
+        return Ok(preferred);
+    }
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0))
+        .map_err(|e| format!("Failed to find a free local port: {}", e))?;
+    listener
+        .local_addr()
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Failed to find a free local port: {}", e))
+}
+
+// Run one port forwarding session until it is stopped, exits or its task goes away
+async fn run_session(
+    app: &AppHandle,
+    tunnel: &TunnelDefinition,
+    run_id: u64,
+    stop_rx: &mut oneshot::Receiver<()>,
+) -> SessionEnd {
+    let lost = |error: String, was_connected: bool| SessionEnd::Lost {
+        error,
+        was_connected,
+    };
+
+    let (task_arn, runtime_id) = match resolve_target(tunnel).await {
+        Ok(target) => target,
+        Err(e) => return lost(e, false),
+    };
+    let local_port = match pick_local_port(tunnel.local_port) {
+        Ok(port) => port,
+        Err(e) => return lost(e, false),
+    };
+    let task_id = task_id_from_arn(&task_arn);
+    update_status(app, &tunnel.id, run_id, |s| {
+        s.state = TunnelState::Starting;
+        s.task = Some(task_id.clone());
+        s.local_port = Some(local_port);
+    });
+
+    let ssm_target = format!(
+        "ecs:{}_{}_{}",
+        cluster_name_from_arn(&tunnel.cluster),
+        task_id,
+        runtime_id
+    );
+    let parameters = json!({
+        "host": [tunnel.remote_host],
+        "portNumber": [tunnel.remote_port.to_string()],
+        "localPortNumber": [local_port.to_string()],
+    })
+    .to_string();
+
+    let mut child = match TokioCommand::new("aws")
+        .env("PATH", get_path_with_common_locations())
+        .args([
+            "ssm",
+            "start-session",
+            "--target",
+            &ssm_target,
+            "--document-name",
+            PORT_FORWARD_DOCUMENT,
+            "--parameters",
+            &parameters,
+            "--region",
+            &tunnel.region,
+            "--profile",
+            &tunnel.profile,
+        ])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return lost(format!("Failed to spawn port forward: {}", e), false),
+    };
+
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return lost("Failed to capture port forward output".to_string(), false);
+    };
+    let mut stdout = BufReader::new(stdout).lines();
+    let mut stderr = BufReader::new(stderr).lines();
+    let mut stderr_open = true;
+    let mut last_error = None;
+    let mut connected = false;
+
+    let mut health = tokio::time::interval(HEALTH_INTERVAL);
+    health.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = &mut *stop_rx => {
+                let _ = child.kill().await;
+                return SessionEnd::Stopped;
+            }
+            line = stdout.next_line() => match line {
+                Ok(Some(line)) => {
+                    if !connected && line.contains(READY_MARKER) {
+                        connected = true;
+                        update_status(app, &tunnel.id, run_id, |s| {
+                            s.state = TunnelState::Connected;
+                            s.last_error = None;
+                            s.connected_since = Some(chrono::Utc::now().timestamp_millis());
+                        });
+                    }
+                }
+                _ => {
+                    let status = child.wait().await;
+                    let error = last_error.unwrap_or_else(|| match status {
+                        Ok(status) => format!("Port forward exited with {}", status),
+                        Err(e) => format!("Port forward exited: {}", e),
+                    });
+                    return lost(error, connected);
+                }
+            },
+            line = stderr.next_line(), if stderr_open => match line {
+                Ok(Some(line)) if !line.trim().is_empty() => last_error = Some(line.trim().to_string()),
+                Ok(Some(_)) => {}
+                _ => stderr_open = false,
+            },
+            _ = health.tick() => {
+                if !task_running(tunnel, &task_arn).await {
+                    let _ = child.kill().await;
+                    return lost(format!("Task {} is no longer running", task_id), connected);
+                }
+            }
+        }
+    }
+}
+
+// Keep a tunnel up until it is stopped, moving to a new task whenever the
+// session or its task goes away
+async fn supervise(
+    app: AppHandle,
+    tunnel: TunnelDefinition,
+    run_id: u64,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let mut retry = RETRY_MIN;
+    loop {
+        let (error, was_connected) = match run_session(&app, &tunnel, run_id, &mut stop_rx).await {
+            SessionEnd::Stopped => return,
+            SessionEnd::Lost {
+                error,
+                was_connected,
+            } => (error, was_connected),
+        };
+        eprintln!("[DEBUG] Tunnel {} lost: {}", tunnel.name, error);
+
+        if was_connected {
+            retry = RETRY_MIN;
+        }
+        update_status(&app, &tunnel.id, run_id, |s| {
+            s.state = TunnelState::Reconnecting;
+            s.restarts += 1;
+            s.last_error = Some(error);
+            s.connected_since = None;
+        });
+
+        tokio::select! {
+            _ = &mut stop_rx => return,
+            _ = tokio::time::sleep(retry) => {}
+        }
+        retry = (retry * 2).min(RETRY_MAX);
+    }
+}
+
+fn start(app: &AppHandle, tunnel: TunnelDefinition) -> TunnelStatus {
+    let mut running = RUNNING_TUNNELS.lock().unwrap();
+    let running = running.get_or_insert_with(HashMap::new);
+    if let Some(existing) = running.get(&tunnel.id) {
+        return existing.status.clone();
+    }
+
+    let run_id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let mut status = stopped_status(&tunnel);
+    status.state = TunnelState::Starting;
+    running.insert(
+        tunnel.id.clone(),
+        RunningTunnel {
+            run_id,
+            status: status.clone(),
+            stop: Some(stop_tx),
+        },
+    );
+
+    let _ = app.emit("tunnel:status", status.clone());
+    tauri::async_runtime::spawn(supervise(app.clone(), tunnel, run_id, stop_rx));
+    status
+}
+
+fn stop(app: &AppHandle, tunnel: &TunnelDefinition) -> bool {
+    let removed = {
+        let mut running = RUNNING_TUNNELS.lock().unwrap();
+        running.as_mut().and_then(|r| r.remove(&tunnel.id))
+    };
+    let Some(mut removed) = removed else {
+        return false;
+    };
+    if let Some(stop) = removed.stop.take() {
+        let _ = stop.send(());
+    }
+    let _ = app.emit("tunnel:status", stopped_status(tunnel));
+    true
+}
+
+fn stack_status(stack: &TunnelStack, tunnels: &[TunnelDefinition]) -> StackStatus {
+    let statuses: Vec<TunnelStatus> = stack
+        .tunnel_ids
+        .iter()
+        .filter_map(|id| tunnels.iter().find(|t| &t.id == id))
+        .map(current_status)
+        .collect();
+
+    let running = statuses
+        .iter()
+        .filter(|s| s.state != TunnelState::Stopped)
+        .count();
+    let connected = statuses
+        .iter()
+        .filter(|s| s.state == TunnelState::Connected)
+        .count();
+    let health = if running == 0 {
+        StackHealth::Stopped
+    } else if connected == statuses.len() {
+        StackHealth::Healthy
+    } else if connected > 0 {
+        StackHealth::Degraded
+    } else {
+        StackHealth::Down
+    };
+
+    StackStatus {
+        stack_id: stack.id.clone(),
+        name: stack.name.clone(),
+        health,
+        tunnels: statuses,
+    }
+}
+
+fn find_stack(
+    app: &AppHandle,
+    stack_id: &str,
+) -> Result<(TunnelStack, Vec<TunnelDefinition>), String> {
+    let stack = load_stacks(app)?
+        .into_iter()
+        .find(|s| s.id == stack_id)
+        .ok_or("Tunnel stack not found")?;
+    let tunnels = load_tunnels(app)?;
+    Ok((stack, tunnels))
+}
+
+fn start_stack(app: &AppHandle, stack: &TunnelStack, tunnels: &[TunnelDefinition]) {
+    for tunnel in tunnels.iter().filter(|t| stack.tunnel_ids.contains(&t.id)) {
+        start(app, tunnel.clone());
+    }
+}
+
+// Bring up the stacks marked to start with the app
+pub(crate) fn start_auto_stacks(app: AppHandle) {
+    let (stacks, tunnels) = match (load_stacks(&app), load_tunnels(&app)) {
+        (Ok(stacks), Ok(tunnels)) => (stacks, tunnels),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("[DEBUG] Failed to load tunnel stacks: {}", e);
+            return;
+        }
+    };
+    for stack in stacks.iter().filter(|s| s.auto_start) {
+        start_stack(&app, stack, &tunnels);
+    }
+}
+
+#[command]
+pub fn list_tunnels(window: Window) -> Result<Vec<TunnelDefinition>, String> {
+    load_tunnels(window.app_handle())
+}
+
+#[command]
+pub fn save_tunnel(
+    window: Window,
+    mut tunnel: TunnelDefinition,
+) -> Result<TunnelDefinition, String> {
+    if tunnel.remote_host.trim().is_empty() || tunnel.remote_port == 0 {
+        return Err("A tunnel needs a remote host and port".to_string());
+    }
+
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+    let mut tunnels = load_tunnels(app)?;
+
+    if tunnel.id.is_empty() {
+        tunnel.id = uuid::Uuid::new_v4().to_string();
+    }
+    match tunnels.iter_mut().find(|t| t.id == tunnel.id) {
+        Some(existing) => *existing = tunnel.clone(),
+        None => tunnels.push(tunnel.clone()),
+    }
+    storage::save(app, TUNNELS_STORE, TUNNELS_KEY, &tunnels)?;
+
+    // Restart a running tunnel so it picks up the new definition
+    if stop(app, &tunnel) {
+        start(app, tunnel.clone());
+    }
+    Ok(tunnel)
+}
+
+#[command]
+pub fn delete_tunnel(window: Window, tunnel_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+
+    let mut tunnels = load_tunnels(app)?;
+    if let Some(tunnel) = tunnels.iter().find(|t| t.id == tunnel_id) {
+        stop(app, tunnel);
+    }
+    tunnels.retain(|t| t.id != tunnel_id);
+    storage::save(app, TUNNELS_STORE, TUNNELS_KEY, &tunnels)?;
+
+    let mut stacks = load_stacks(app)?;
+    for stack in stacks.iter_mut() {
+        stack.tunnel_ids.retain(|id| id != &tunnel_id);
+    }
+    storage::save(app, TUNNELS_STORE, STACKS_KEY, &stacks)
+}
+
+#[command]
+pub fn list_tunnel_stacks(window: Window) -> Result<Vec<TunnelStack>, String> {
+    load_stacks(window.app_handle())
+}
+
+#[command]
+pub fn save_tunnel_stack(window: Window, mut stack: TunnelStack) -> Result<TunnelStack, String> {
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+
+    let tunnels = load_tunnels(app)?;
+    if let Some(missing) = stack
+        .tunnel_ids
+        .iter()
+        .find(|id| !tunnels.iter().any(|t| &t.id == *id))
+    {
+        return Err(format!("Tunnel {} not found", missing));
+    }
+
+    let mut stacks = load_stacks(app)?;
+    if stack.id.is_empty() {
+        stack.id = uuid::Uuid::new_v4().to_string();
+    }
+    match stacks.iter_mut().find(|s| s.id == stack.id) {
+        Some(existing) => *existing = stack.clone(),
+        None => stacks.push(stack.clone()),
+    }
+    storage::save(app, TUNNELS_STORE, STACKS_KEY, &stacks)?;
+    Ok(stack)
+}
+
+#[command]
+pub fn delete_tunnel_stack(window: Window, stack_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+
+    let mut stacks = load_stacks(app)?;
+    stacks.retain(|s| s.id != stack_id);
+    storage::save(app, TUNNELS_STORE, STACKS_KEY, &stacks)
+}
+
+#[command]
+pub fn start_tunnel(window: Window, tunnel_id: String) -> Result<TunnelStatus, String> {
+    let app = window.app_handle();
+    let tunnel = load_tunnels(app)?
+        .into_iter()
+        .find(|t| t.id == tunnel_id)
+        .ok_or("Tunnel not found")?;
+    Ok(start(app, tunnel))
+}
+
+#[command]
+pub fn stop_tunnel(window: Window, tunnel_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let tunnel = load_tunnels(app)?
+        .into_iter()
+        .find(|t| t.id == tunnel_id)
+        .ok_or("Tunnel not found")?;
+    stop(app, &tunnel);
+    Ok(())
+}
+
+#[command]
+pub fn start_tunnel_stack(window: Window, stack_id: String) -> Result<StackStatus, String> {
+    let app = window.app_handle();
+    let (stack, tunnels) = find_stack(app, &stack_id)?;
+    start_stack(app, &stack, &tunnels);
+    Ok(stack_status(&stack, &tunnels))
+}
+
+#[command]
+pub fn stop_tunnel_stack(window: Window, stack_id: String) -> Result<StackStatus, String> {
+    let app = window.app_handle();
+    let (stack, tunnels) = find_stack(app, &stack_id)?;
+    for tunnel in tunnels.iter().filter(|t| stack.tunnel_ids.contains(&t.id)) {
+        stop(app, tunnel);
+    }
+    Ok(stack_status(&stack, &tunnels))
+}
+
+#[command]
+pub fn get_tunnel_stack_status(window: Window) -> Result<Vec<StackStatus>, String> {
+    let app = window.app_handle();
+    let tunnels = load_tunnels(app)?;
+    Ok(load_stacks(app)?
+        .iter()
+        .map(|stack| stack_status(stack, &tunnels))
+        .collect())
+}
+
+#[command]
+pub fn get_tunnel_status(window: Window) -> Result<Vec<TunnelStatus>, String> {
+    let tunnels = load_tunnels(window.app_handle())?;
+    Ok(tunnels.iter().map(current_status).collect())
+}
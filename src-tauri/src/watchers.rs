@@ -0,0 +1,817 @@
+use aws_sdk_ecs::error::DisplayErrorContext;
+use aws_sdk_ecs::types::DesiredStatus;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, Window};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::aws::task_id_from_arn;
+use crate::ecs_api;
+use crate::services::{describe_service, ServiceDetails};
+use crate::storage;
+
+const WATCHERS_STORE: &str = ".watchers.dat";
+const WATCHERS_KEY: &str = "watchers";
+const HISTORY_KEY: &str = "history";
+const MAX_HISTORY_PER_WATCHER: usize = 100;
+const TICK_INTERVAL: Duration = Duration::from_secs(15);
+const MIN_INTERVAL_SECS: u64 = 30;
+// DescribeTasks accepts at most this many tasks per call
+const DESCRIBE_TASKS_BATCH: usize = 100;
+
+// Guards read-modify-write of the watchers store between commands and the watch loop
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+static WATCH_STATES: Mutex<Option<HashMap<String, WatchState>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceWatcher {
+    #[serde(default)]
+    id: String,
+    profile: String,
+    region: String,
+    cluster: String,
+    service: String,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default = "default_interval_secs")]
+    interval_secs: u64,
+    #[serde(default = "default_true")]
+    notify_deployments: bool,
+    #[serde(default = "default_true")]
+    notify_task_failures: bool,
+    // Notify once this many tasks stopped with a non-zero exit code within the window
+    #[serde(default = "default_failure_threshold")]
+    failure_threshold: usize,
+    #[serde(default = "default_failure_window_secs")]
+    failure_window_secs: u64,
+    #[serde(default)]
+    created_at: i64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_failure_threshold() -> usize {
+    3
+}
+
+fn default_failure_window_secs() -> u64 {
+    900
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WatchEventKind {
+    DeploymentStarted,
+    RolloutCompleted,
+    RolloutFailed,
+    TaskFailed,
+    // failure_threshold failed tasks within failure_window_secs
+    RepeatedTaskFailures,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchEvent {
+    watcher_id: String,
+    service: String,
+    kind: WatchEventKind,
+    message: String,
+    at: i64,
+    #[serde(default)]
+    deployment_id: Option<String>,
+    #[serde(default)]
+    task_arn: Option<String>,
+}
+
+// What the previous poll saw. The first poll only records, so starting the
+// app does not replay every rollout and stopped task still known to ECS.
+#[derive(Default)]
+struct WatchState {
+    primed: bool,
+    checking: bool,
+    last_check: Option<Instant>,
+    rollout_states: HashMap<String, String>,
+    seen_stopped: HashSet<String>,
+    recent_failures: VecDeque<i64>,
+    last_failure_alert: Option<i64>,
+    last_error: Option<String>,
+}
+
+struct StoppedTask {
+    task_arn: String,
+    stopped_at: i64,
+    // Name, exit code and reason of each container that exited non-zero
+    failed_containers: Vec<(String, i32, Option<String>)>,
+    stopped_reason: Option<String>,
+}
+
+fn load_watchers(app: &AppHandle) -> Result<Vec<ServiceWatcher>, String> {
+    storage::load(app, WATCHERS_STORE, WATCHERS_KEY)
+}
+
+fn load_history(app: &AppHandle) -> Result<HashMap<String, Vec<WatchEvent>>, String> {
+    storage::load(app, WATCHERS_STORE, HISTORY_KEY)
+}
+
+fn service_name(service: &str) -> &str {
+    service.rsplit('/').next().unwrap_or(service)
+}
+
+// Whether any watcher is enabled, closing the window then hides it to the tray
+pub(crate) fn has_active_watchers(app: &AppHandle) -> bool {
+    load_watchers(app).is_ok_and(|w| w.iter().any(|w| w.enabled))
+}
+
+async fn stopped_tasks(watcher: &ServiceWatcher) -> Result<Vec<StoppedTask>, String> {
+    let client = ecs_api::client(&watcher.profile, &watcher.region).await;
+    let mut arns = Vec::new();
+    let mut next_token = None;
+    loop {
+        let out = client
+            .list_tasks()
+            .cluster(&watcher.cluster)
+            .service_name(&watcher.service)
+            .desired_status(DesiredStatus::Stopped)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(|e| format!("ListTasks failed: {}", DisplayErrorContext(e)))?;
+        arns.extend(out.task_arns().iter().cloned());
+        next_token = out.next_token().map(|s| s.to_string());
+        if next_token.is_none() {
+            break;
+        }
+    }
+
+    let mut tasks = Vec::new();
+    for batch in arns.chunks(DESCRIBE_TASKS_BATCH) {
+        let out = client
+            .describe_tasks()
+            .cluster(&watcher.cluster)
+            .set_tasks(Some(batch.to_vec()))
+            .send()
+            .await
+            .map_err(|e| format!("DescribeTasks failed: {}", DisplayErrorContext(e)))?;
+
+        for task in out.tasks() {
+            // Tasks are listed while still stopping, wait until they are gone
+            if task.last_status() != Some("STOPPED") {
+                continue;
+            }
+            tasks.push(StoppedTask {
+                task_arn: task.task_arn().unwrap_or_default().to_string(),
+                stopped_at: task
+                    .stopped_at()
+                    .and_then(|t| t.to_millis().ok())
+                    .unwrap_or_else(|| Utc::now().timestamp_millis()),
+                failed_containers: task
+                    .containers()
+                    .iter()
+                    .filter_map(|c| {
+                        let code = c.exit_code().filter(|code| *code != 0)?;
+                        Some((
+                            c.name().unwrap_or_default().to_string(),
+                            code,
+                            c.reason().map(|s| s.to_string()),
+                        ))
+                    })
+                    .collect(),
+                stopped_reason: task.stopped_reason().map(|s| s.to_string()),
+            });
+        }
+    }
+    Ok(tasks)
+}
+
+// Compare a fresh poll with the previous one and return what changed
+async fn check(watcher: &ServiceWatcher) -> Result<Vec<WatchEvent>, String> {
+    let service = describe_service(
+        &watcher.profile,
+        &watcher.region,
+        &watcher.cluster,
+        &watcher.service,
+    )
+    .await?;
+    let stopped = stopped_tasks(watcher).await?;
+
+    let mut states = WATCH_STATES.lock().unwrap();
+    let state = states
+        .get_or_insert_with(HashMap::new)
+        .entry(watcher.id.clone())
+        .or_default();
+    Ok(compare_poll(
+        watcher,
+        state,
+        &service,
+        stopped,
+        Utc::now().timestamp_millis(),
+    ))
+}
+
+// Events between the state of the previous poll and this one, which becomes
+// the new state
+fn compare_poll(
+    watcher: &ServiceWatcher,
+    state: &mut WatchState,
+    service: &ServiceDetails,
+    stopped: Vec<StoppedTask>,
+    now: i64,
+) -> Vec<WatchEvent> {
+    let name = service_name(&watcher.service).to_string();
+    let event = |kind, message: String| WatchEvent {
+        watcher_id: watcher.id.clone(),
+        service: name.clone(),
+        kind,
+        message,
+        at: now,
+        deployment_id: None,
+        task_arn: None,
+    };
+    let mut events = Vec::new();
+
+    let mut rollout_states = HashMap::new();
+    for deployment in &service.deployments {
+        // Without a circuit breaker there is no rollout state, judge it by the counts
+        let rollout = deployment.rollout_state.clone().unwrap_or_else(|| {
+            let settled = deployment.running_count == deployment.desired_count
+                && deployment.pending_count == 0;
+            if settled { "COMPLETED" } else { "IN_PROGRESS" }.to_string()
+        });
+        let revision = deployment
+            .task_definition
+            .as_deref()
+            .map(|td| td.rsplit('/').next().unwrap_or(td))
+            .unwrap_or("unknown task definition");
+
+        let previous = state.rollout_states.get(&deployment.id);
+        if state.primed && previous != Some(&rollout) {
+            let change = match (previous, rollout.as_str()) {
+                (None, "IN_PROGRESS") => Some((
+                    WatchEventKind::DeploymentStarted,
+                    format!("Deployment of {} started", revision),
+                )),
+                (_, "COMPLETED") => Some((
+                    WatchEventKind::RolloutCompleted,
+                    format!("Rollout of {} completed", revision),
+                )),
+                (_, "FAILED") => Some((
+                    WatchEventKind::RolloutFailed,
+                    format!(
+                        "Rollout of {} failed: {}",
+                        revision,
+                        deployment
+                            .rollout_state_reason
+                            .as_deref()
+                            .unwrap_or("no reason given")
+                    ),
+                )),
+                _ => None,
+            };
+            if let Some((kind, message)) = change {
+                events.push(WatchEvent {
+                    deployment_id: Some(deployment.id.clone()),
+                    ..event(kind, message)
+                });
+            }
+        }
+        rollout_states.insert(deployment.id.clone(), rollout);
+    }
+    state.rollout_states = rollout_states;
+
+    let window_start = now - (watcher.failure_window_secs as i64) * 1000;
+    // ECS forgets stopped tasks after about an hour, so only the listed ones are kept
+    let previously_seen = std::mem::replace(
+        &mut state.seen_stopped,
+        stopped.iter().map(|t| t.task_arn.clone()).collect(),
+    );
+    for task in stopped {
+        if !state.primed
+            || previously_seen.contains(&task.task_arn)
+            || task.failed_containers.is_empty()
+        {
+            continue;
+        }
+        let containers = task
+            .failed_containers
+            .iter()
+            .map(|(name, code, reason)| match reason {
+                Some(reason) => format!("{} exited {} ({})", name, code, reason),
+                None => format!("{} exited {}", name, code),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = match task.stopped_reason {
+            Some(ref reason) => format!(
+                "Task {} stopped: {}; {}",
+                task_id_from_arn(&task.task_arn),
+                containers,
+                reason
+            ),
+            None => format!(
+                "Task {} stopped: {}",
+                task_id_from_arn(&task.task_arn),
+                containers
+            ),
+        };
+        events.push(WatchEvent {
+            task_arn: Some(task.task_arn.clone()),
+            ..event(WatchEventKind::TaskFailed, message)
+        });
+        if task.stopped_at >= window_start {
+            state.recent_failures.push_back(task.stopped_at);
+        }
+    }
+
+    while state
+        .recent_failures
+        .front()
+        .is_some_and(|t| *t < window_start)
+    {
+        state.recent_failures.pop_front();
+    }
+    let alerted_recently = state.last_failure_alert.is_some_and(|t| t >= window_start);
+    if state.recent_failures.len() >= watcher.failure_threshold.max(1) && !alerted_recently {
+        state.last_failure_alert = Some(now);
+        events.push(event(
+            WatchEventKind::RepeatedTaskFailures,
+            format!(
+                "{} tasks stopped with a non-zero exit code in the last {} minutes",
+                state.recent_failures.len(),
+                watcher.failure_window_secs / 60
+            ),
+        ));
+    }
+
+    state.primed = true;
+    state.last_error = None;
+    events
+}
+
+fn should_notify(watcher: &ServiceWatcher, kind: &WatchEventKind) -> bool {
+    match kind {
+        WatchEventKind::RolloutCompleted | WatchEventKind::RolloutFailed => {
+            watcher.notify_deployments
+        }
+        WatchEventKind::RepeatedTaskFailures => watcher.notify_task_failures,
+        _ => false,
+    }
+}
+
+fn record_events(app: &AppHandle, watcher: &ServiceWatcher, events: &[WatchEvent]) {
+    if events.is_empty() {
+        return;
+    }
+
+    let saved = {
+        let _guard = STORE_LOCK.lock().unwrap();
+        load_history(app).and_then(|mut history| {
+            let entries = history.entry(watcher.id.clone()).or_default();
+            entries.extend(events.iter().cloned());
+            if entries.len() > MAX_HISTORY_PER_WATCHER {
+                let excess = entries.len() - MAX_HISTORY_PER_WATCHER;
+                entries.drain(..excess);
+            }
+            storage::save(app, WATCHERS_STORE, HISTORY_KEY, &history)
+        })
+    };
+    if let Err(e) = saved {
+        eprintln!("[DEBUG] Failed to save watcher history: {}", e);
+    }
+
+    for event in events {
+        if should_notify(watcher, &event.kind) {
+            let _ = app
+                .notification()
+                .builder()
+                .title(&event.service)
+                .body(&event.message)
+                .show();
+        }
+        let _ = app.emit("watcher:event", event);
+    }
+}
+
+async fn run_check(app: &AppHandle, watcher: &ServiceWatcher) {
+    let events = match check(watcher).await {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("[DEBUG] Watcher {} check failed: {}", watcher.id, e);
+            // Report an error once, not on every poll while it persists
+            let mut states = WATCH_STATES.lock().unwrap();
+            let state = states
+                .get_or_insert_with(HashMap::new)
+                .entry(watcher.id.clone())
+                .or_default();
+            if state.last_error.as_deref() == Some(e.as_str()) {
+                vec![]
+            } else {
+                state.last_error = Some(e.clone());
+                vec![WatchEvent {
+                    watcher_id: watcher.id.clone(),
+                    service: service_name(&watcher.service).to_string(),
+                    kind: WatchEventKind::Error,
+                    message: e,
+                    at: Utc::now().timestamp_millis(),
+                    deployment_id: None,
+                    task_arn: None,
+                }]
+            }
+        }
+    };
+    record_events(app, watcher, &events);
+}
+
+// Claim a watcher for a check when its interval has passed and none is running
+fn claim_due(watcher: &ServiceWatcher) -> bool {
+    let interval = Duration::from_secs(watcher.interval_secs.max(MIN_INTERVAL_SECS));
+    let mut states = WATCH_STATES.lock().unwrap();
+    let state = states
+        .get_or_insert_with(HashMap::new)
+        .entry(watcher.id.clone())
+        .or_default();
+    if state.checking || state.last_check.is_some_and(|t| t.elapsed() < interval) {
+        return false;
+    }
+    state.checking = true;
+    state.last_check = Some(Instant::now());
+    true
+}
+
+fn release(watcher_id: &str) {
+    if let Some(state) = WATCH_STATES
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|s| s.get_mut(watcher_id))
+    {
+        state.checking = false;
+    }
+}
+
+// Poll watched services in the background for as long as the app runs,
+// including while the window is hidden to the tray
+pub(crate) fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let watchers = match load_watchers(&app) {
+                Ok(watchers) => watchers,
+                Err(e) => {
+                    eprintln!("[DEBUG] Failed to load watchers: {}", e);
+                    continue;
+                }
+            };
+
+            for watcher in watchers.into_iter().filter(|w| w.enabled && claim_due(w)) {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    run_check(&app, &watcher).await;
+                    release(&watcher.id);
+                });
+            }
+        }
+    });
+}
+
+#[command]
+pub fn list_watchers(window: Window) -> Result<Vec<ServiceWatcher>, String> {
+    load_watchers(window.app_handle())
+}
+
+#[command]
+pub fn save_watcher(window: Window, mut watcher: ServiceWatcher) -> Result<ServiceWatcher, String> {
+    if watcher.service.trim().is_empty() {
+        return Err("A watcher needs a service".to_string());
+    }
+    watcher.interval_secs = watcher.interval_secs.max(MIN_INTERVAL_SECS);
+
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+    let mut watchers = load_watchers(app)?;
+
+    if watcher.id.is_empty() {
+        watcher.id = uuid::Uuid::new_v4().to_string();
+        watcher.created_at = Utc::now().timestamp_millis();
+    }
+
+    match watchers.iter_mut().find(|w| w.id == watcher.id) {
+        Some(existing) => {
+            watcher.created_at = existing.created_at;
+            // A different service starts from a fresh baseline
+            if existing.service != watcher.service || existing.cluster != watcher.cluster {
+                if let Some(ref mut states) = *WATCH_STATES.lock().unwrap() {
+                    states.remove(&watcher.id);
+                }
+            }
+            *existing = watcher.clone();
+        }
+        None => watchers.push(watcher.clone()),
+    }
+
+    storage::save(app, WATCHERS_STORE, WATCHERS_KEY, &watchers)?;
+    Ok(watcher)
+}
+
+#[command]
+pub fn delete_watcher(window: Window, watcher_id: String) -> Result<(), String> {
+    let app = window.app_handle();
+    let _guard = STORE_LOCK.lock().unwrap();
+
+    let mut watchers = load_watchers(app)?;
+    watchers.retain(|w| w.id != watcher_id);
+    storage::save(app, WATCHERS_STORE, WATCHERS_KEY, &watchers)?;
+
+    if let Some(ref mut states) = *WATCH_STATES.lock().unwrap() {
+        states.remove(&watcher_id);
+    }
+
+    let mut history = load_history(app)?;
+    history.remove(&watcher_id);
+    storage::save(app, WATCHERS_STORE, HISTORY_KEY, &history)
+}
+
+#[command]
+pub fn get_watcher_history(window: Window, watcher_id: String) -> Result<Vec<WatchEvent>, String> {
+    let mut history = load_history(window.app_handle())?;
+    Ok(history.remove(&watcher_id).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ServiceDeployment;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn watcher() -> ServiceWatcher {
+        ServiceWatcher {
+            id: "watcher".to_string(),
+            profile: "default".to_string(),
+            region: "eu-west-1".to_string(),
+            cluster: "main".to_string(),
+            service: "api".to_string(),
+            enabled: true,
+            interval_secs: default_interval_secs(),
+            notify_deployments: true,
+            notify_task_failures: true,
+            failure_threshold: 2,
+            failure_window_secs: 900,
+            created_at: 0,
+        }
+    }
+
+    fn deployment(id: &str, revision: u32, rollout_state: &str) -> ServiceDeployment {
+        ServiceDeployment {
+            id: id.to_string(),
+            status: Some("PRIMARY".to_string()),
+            task_definition: Some(format!(
+                "arn:aws:ecs:eu-west-1:123456789012:task-definition/api:{}",
+                revision
+            )),
+            desired_count: 2,
+            running_count: 2,
+            pending_count: 0,
+            failed_tasks: 0,
+            rollout_state: Some(rollout_state.to_string()),
+            rollout_state_reason: None,
+            launch_type: Some("FARGATE".to_string()),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn service(deployments: Vec<ServiceDeployment>) -> ServiceDetails {
+        ServiceDetails {
+            service_name: "api".to_string(),
+            service_arn: "arn:aws:ecs:eu-west-1:123456789012:service/main/api".to_string(),
+            cluster_arn: None,
+            status: Some("ACTIVE".to_string()),
+            desired_count: 2,
+            running_count: 2,
+            pending_count: 0,
+            launch_type: Some("FARGATE".to_string()),
+            capacity_providers: Vec::new(),
+            platform_version: None,
+            task_definition: None,
+            deployments,
+            events: Vec::new(),
+            load_balancers: Vec::new(),
+            network_configuration: None,
+            enable_execute_command: true,
+            steady: true,
+            created_at: None,
+        }
+    }
+
+    fn stopped(id: &str, stopped_at: i64, exit_code: i32) -> StoppedTask {
+        StoppedTask {
+            task_arn: format!("arn:aws:ecs:eu-west-1:123456789012:task/main/{}", id),
+            stopped_at,
+            failed_containers: if exit_code == 0 {
+                Vec::new()
+            } else {
+                vec![("web".to_string(), exit_code, None)]
+            },
+            stopped_reason: Some("Essential container in task exited".to_string()),
+        }
+    }
+
+    fn kinds(events: &[WatchEvent]) -> Vec<String> {
+        events.iter().map(|e| format!("{:?}", e.kind)).collect()
+    }
+
+    #[test]
+    fn first_poll_only_records() {
+        let mut state = WatchState::default();
+        let events = compare_poll(
+            &watcher(),
+            &mut state,
+            &service(vec![deployment("ecs-svc/1", 1, "IN_PROGRESS")]),
+            vec![stopped("old", 0, 1)],
+            10 * MINUTE,
+        );
+        assert!(events.is_empty());
+        assert!(state.primed);
+        assert!(state.seen_stopped.len() == 1);
+    }
+
+    #[test]
+    fn rollout_transitions() {
+        let watcher = watcher();
+        let mut state = WatchState::default();
+        compare_poll(
+            &watcher,
+            &mut state,
+            &service(vec![deployment("ecs-svc/1", 1, "COMPLETED")]),
+            Vec::new(),
+            0,
+        );
+
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(vec![
+                deployment("ecs-svc/2", 2, "IN_PROGRESS"),
+                deployment("ecs-svc/1", 1, "COMPLETED"),
+            ]),
+            Vec::new(),
+            MINUTE,
+        );
+        assert_eq!(kinds(&events), vec!["DeploymentStarted"]);
+        assert_eq!(events[0].deployment_id.as_deref(), Some("ecs-svc/2"));
+        assert_eq!(events[0].message, "Deployment of api:2 started");
+
+        // Nothing changed, nothing to report
+        let unchanged = service(vec![
+            deployment("ecs-svc/2", 2, "IN_PROGRESS"),
+            deployment("ecs-svc/1", 1, "COMPLETED"),
+        ]);
+        assert!(compare_poll(&watcher, &mut state, &unchanged, Vec::new(), 2 * MINUTE).is_empty());
+
+        let mut failed = deployment("ecs-svc/2", 2, "FAILED");
+        failed.rollout_state_reason = Some("circuit breaker triggered".to_string());
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(vec![failed]),
+            Vec::new(),
+            3 * MINUTE,
+        );
+        assert_eq!(kinds(&events), vec!["RolloutFailed"]);
+        assert_eq!(
+            events[0].message,
+            "Rollout of api:2 failed: circuit breaker triggered"
+        );
+    }
+
+    #[test]
+    fn deployments_without_rollout_state_are_judged_by_counts() {
+        let watcher = watcher();
+        let mut state = WatchState::default();
+        compare_poll(&watcher, &mut state, &service(Vec::new()), Vec::new(), 0);
+
+        let mut rolling = deployment("ecs-svc/3", 3, "IN_PROGRESS");
+        rolling.rollout_state = None;
+        rolling.running_count = 1;
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(vec![rolling.clone()]),
+            Vec::new(),
+            MINUTE,
+        );
+        assert_eq!(kinds(&events), vec!["DeploymentStarted"]);
+
+        rolling.running_count = 2;
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(vec![rolling]),
+            Vec::new(),
+            2 * MINUTE,
+        );
+        assert_eq!(kinds(&events), vec!["RolloutCompleted"]);
+    }
+
+    #[test]
+    fn task_failures_are_reported_once_and_counted_in_the_window() {
+        let watcher = watcher();
+        let mut state = WatchState::default();
+        let now = 60 * MINUTE;
+        compare_poll(
+            &watcher,
+            &mut state,
+            &service(Vec::new()),
+            vec![stopped("old", 0, 1)],
+            now,
+        );
+
+        // A clean exit and an already seen task are not failures
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(Vec::new()),
+            vec![
+                stopped("old", 0, 1),
+                stopped("clean", now, 0),
+                stopped("t1", now, 137),
+            ],
+            now,
+        );
+        assert_eq!(kinds(&events), vec!["TaskFailed"]);
+        assert_eq!(
+            events[0].message,
+            "Task t1 stopped: web exited 137; Essential container in task exited"
+        );
+
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(Vec::new()),
+            vec![stopped("t1", now, 137), stopped("t2", now + MINUTE, 1)],
+            now + MINUTE,
+        );
+        assert_eq!(kinds(&events), vec!["TaskFailed", "RepeatedTaskFailures"]);
+
+        // No second alert within the same window
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(Vec::new()),
+            vec![stopped("t3", now + 2 * MINUTE, 1)],
+            now + 2 * MINUTE,
+        );
+        assert_eq!(kinds(&events), vec!["TaskFailed"]);
+    }
+
+    #[test]
+    fn old_failures_leave_the_window() {
+        let watcher = watcher();
+        let mut state = WatchState::default();
+        compare_poll(&watcher, &mut state, &service(Vec::new()), Vec::new(), 0);
+
+        compare_poll(
+            &watcher,
+            &mut state,
+            &service(Vec::new()),
+            vec![stopped("t1", MINUTE, 1)],
+            MINUTE,
+        );
+        let later = MINUTE + 16 * MINUTE;
+        let events = compare_poll(
+            &watcher,
+            &mut state,
+            &service(Vec::new()),
+            vec![stopped("t2", later, 1)],
+            later,
+        );
+        assert_eq!(kinds(&events), vec!["TaskFailed"]);
+        assert_eq!(state.recent_failures.len(), 1);
+    }
+
+    #[test]
+    fn notifications_follow_watcher_settings() {
+        let mut watcher = watcher();
+        assert!(should_notify(&watcher, &WatchEventKind::RolloutFailed));
+        assert!(!should_notify(&watcher, &WatchEventKind::TaskFailed));
+        watcher.notify_deployments = false;
+        assert!(!should_notify(&watcher, &WatchEventKind::RolloutCompleted));
+        assert!(should_notify(
+            &watcher,
+            &WatchEventKind::RepeatedTaskFailures
+        ));
+    }
+}